@@ -0,0 +1,71 @@
+//! How long a turn takes to play on a `Board` and on a `PackedBoard`: inserting the spare, finding
+//! everywhere the player can move to, and undoing the insertion, as a search would.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use labyrinth::packed::PackedBoard;
+use labyrinth::{Board, BoardLayout, Player};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashSet;
+
+fn board() -> Board {
+  let players = HashSet::from([Player::Player1, Player::Player2]);
+  let layout = BoardLayout::standard(Board::STANDARD_SIZE).unwrap();
+  Board::new(&mut ChaCha8Rng::seed_from_u64(0), &players, &layout).unwrap()
+}
+
+fn apply_turn(c: &mut Criterion) {
+  let mut board = board();
+  let mut packed = PackedBoard::try_from(&board).unwrap();
+  let insertions = board.legal_insertions();
+
+  c.bench_function("board turn", |b| {
+    b.iter(|| {
+      for (location, rotation) in &insertions {
+        let insertion = board.insert_spare(*location, *rotation).unwrap();
+        let from = board.player_location(&Player::Player1).unwrap();
+        black_box(board.reachable_from(&from));
+        board.undo_insertion(&insertion);
+      }
+    })
+  });
+
+  c.bench_function("packed turn", |b| {
+    b.iter(|| {
+      for (location, rotation) in &insertions {
+        let insertion = packed.insert_spare(*location, *rotation).unwrap();
+        let from = packed.pawn(Player::Player1).unwrap();
+        black_box(packed.reachable(&from).unwrap());
+        packed.undo_insertion(&insertion);
+      }
+    })
+  });
+}
+
+fn reachable(c: &mut Criterion) {
+  let board = board();
+  let packed = PackedBoard::try_from(&board).unwrap();
+  let from = board.player_location(&Player::Player1).unwrap();
+
+  c.bench_function("board reachable", |b| {
+    b.iter(|| board.reachable_from(black_box(&from)))
+  });
+  c.bench_function("packed reachable", |b| {
+    b.iter(|| packed.reachable(black_box(&from)).unwrap())
+  });
+}
+
+fn convert(c: &mut Criterion) {
+  let board = board();
+  let packed = PackedBoard::try_from(&board).unwrap();
+
+  c.bench_function("pack board", |b| {
+    b.iter(|| PackedBoard::try_from(black_box(&board)).unwrap())
+  });
+  c.bench_function("unpack board", |b| {
+    b.iter(|| Board::from(black_box(&packed)))
+  });
+}
+
+criterion_group!(benches, apply_turn, reachable, convert);
+criterion_main!(benches);
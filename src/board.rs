@@ -1,7 +1,7 @@
 extern crate strum_macros;
 extern crate unicode_width;
 use crate::emoji::Emoji;
-use crate::errors::{GenericResult, LocationError, MoveError};
+use crate::errors::{LabyrinthError, LabyrinthResult};
 
 use itertools::Itertools;
 use rand::{
@@ -9,11 +9,13 @@ use rand::{
   seq::SliceRandom,
   Rng,
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
 use std::convert::From;
 use std::fmt;
 use std::iter::Iterator;
+use strum::IntoEnumIterator;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Hash, PartialEq, Eq, Copy, Clone, strum_macros::Display, strum_macros::EnumIter)]
 pub enum Item {
   Chest,
@@ -79,7 +81,8 @@ impl fmt::Debug for Item {
   }
 }
 
-#[derive(Debug, Hash, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Hash, Copy, Clone, Eq, PartialEq, strum_macros::EnumIter)]
 pub enum Player {
   Player1,
   Player2,
@@ -104,7 +107,8 @@ impl fmt::Display for Player {
   }
 }
 
-#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Hash, Copy, Clone, PartialEq, Eq)]
 pub enum TileMarking {
   Item(Item),
   PlayerStart(Player),
@@ -128,7 +132,8 @@ impl fmt::Display for TileMarking {
   }
 }
 
-#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub struct Tile {
   pub marking: Option<TileMarking>,
   pub path_up: bool,
@@ -184,7 +189,7 @@ impl fmt::Debug for Tile {
 }
 
 impl Tile {
-  const CORNER_RIGHT_DOWN: Tile = Tile {
+  pub const CORNER_RIGHT_DOWN: Tile = Tile {
     marking: None,
     path_up: false,
     path_right: true,
@@ -192,7 +197,7 @@ impl Tile {
     path_left: false,
   };
 
-  const CORNER_LEFT_DOWN: Tile = Tile {
+  pub const CORNER_LEFT_DOWN: Tile = Tile {
     marking: None,
     path_up: false,
     path_right: false,
@@ -200,7 +205,7 @@ impl Tile {
     path_left: true,
   };
 
-  const CORNER_LEFT_UP: Tile = Tile {
+  pub const CORNER_LEFT_UP: Tile = Tile {
     marking: None,
     path_up: true,
     path_right: false,
@@ -208,7 +213,7 @@ impl Tile {
     path_left: true,
   };
 
-  const CORNER_RIGHT_UP: Tile = Tile {
+  pub const CORNER_RIGHT_UP: Tile = Tile {
     marking: None,
     path_up: true,
     path_right: true,
@@ -216,7 +221,7 @@ impl Tile {
     path_left: false,
   };
 
-  const TEE_LEFT: Tile = Tile {
+  pub const TEE_LEFT: Tile = Tile {
     marking: None,
     path_up: true,
     path_right: false,
@@ -224,7 +229,7 @@ impl Tile {
     path_left: true,
   };
 
-  const TEE_RIGHT: Tile = Tile {
+  pub const TEE_RIGHT: Tile = Tile {
     marking: None,
     path_up: true,
     path_right: true,
@@ -232,7 +237,7 @@ impl Tile {
     path_left: false,
   };
 
-  const TEE_UP: Tile = Tile {
+  pub const TEE_UP: Tile = Tile {
     marking: None,
     path_up: true,
     path_right: true,
@@ -240,7 +245,7 @@ impl Tile {
     path_left: true,
   };
 
-  const TEE_DOWN: Tile = Tile {
+  pub const TEE_DOWN: Tile = Tile {
     marking: None,
     path_up: false,
     path_right: true,
@@ -248,24 +253,17 @@ impl Tile {
     path_left: true,
   };
 
-  const LINE_VERTICAL: Tile = Tile {
+  pub const LINE_VERTICAL: Tile = Tile {
     marking: None,
     path_up: true,
     path_right: false,
     path_down: true,
     path_left: false,
   };
-
-  const LINE_HORIZONTAL: Tile = Tile {
-    marking: None,
-    path_up: false,
-    path_right: true,
-    path_down: false,
-    path_left: true,
-  };
 }
 
-#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, strum_macros::EnumIter)]
 pub enum Rotation {
   Zero,
   Clockwise90,
@@ -284,6 +282,7 @@ impl Distribution<Rotation> for Standard {
   }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone)]
 pub struct PlacedTile {
   pub tile: Tile,
@@ -301,6 +300,13 @@ impl From<&Tile> for PlacedTile {
   }
 }
 
+impl PlacedTile {
+  /// Whether this tile is the starting tile of the player
+  pub fn is_start_of(&self, player: Player) -> bool {
+    matches!(self.tile.marking, Some(TileMarking::PlayerStart(p)) if p == player)
+  }
+}
+
 impl fmt::Debug for PlacedTile {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     let tile = Tile::from(self);
@@ -346,9 +352,7 @@ impl fmt::Debug for PlacedTile {
       },
       w,
       if tile.path_left { p } else { w },
-      if tile.marking.is_some() {
-        let marking = &tile.marking.unwrap();
-
+      if let Some(marking) = &tile.marking {
         format!("{}{}", marking.emoji(), p.repeat(4 - marking.emoji_width()))
       } else {
         p.repeat(4)
@@ -371,6 +375,7 @@ impl fmt::Debug for PlacedTile {
   }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Hash, Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Location(pub usize, pub usize);
 
@@ -381,10 +386,19 @@ impl fmt::Display for Location {
 }
 
 /// A board containing all tiles placed on the board and the spare extra tile
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone)]
 pub struct Board {
+  #[cfg_attr(feature = "serde", serde(with = "placed_serde"))]
   pub placed: HashMap<Location, PlacedTile>,
   pub spare: Tile,
+  /// How many tiles there are along each side of the board
+  #[cfg_attr(feature = "serde", serde(default = "standard_size"))]
+  size: usize,
+  #[cfg_attr(feature = "serde", serde(skip))]
   graph: BoardGraph,
+  /// Where the last tile was pushed off the board, as inserting here would reverse that move
+  last_push_out: Option<Location>,
 }
 
 pub struct BoardIter<'a> {
@@ -409,7 +423,7 @@ impl<'a> IntoIterator for &'a Board {
   fn into_iter(self) -> Self::IntoIter {
     BoardIter {
       board: self,
-      locations: Board::locations(),
+      locations: Box::new(self.locations()),
     }
   }
 }
@@ -430,16 +444,11 @@ impl fmt::Debug for Board {
       .collect();
 
     let board_row_strs: Vec<String> = tile_line_strs
-      .chunks(7)
+      .chunks(self.size)
       .map(|row_strs| {
         let num_cols = row_strs.first().unwrap().len();
         let col_strs: Vec<String> = (0..num_cols)
-          .map(|col| {
-            row_strs
-              .iter()
-              .map(|tile_str| tile_str.iter().nth(col).unwrap())
-              .join(col_sep)
-          })
+          .map(|col| row_strs.iter().map(|tile_str| &tile_str[col]).join(col_sep))
           .collect();
 
         col_strs.join("\n")
@@ -456,17 +465,18 @@ impl fmt::Debug for Board {
       .first()
       .unwrap();
 
-    let board_str: String = board_row_strs
-      .iter()
-      .intersperse(&row_sep.repeat(num_col_chars))
-      .join("\n");
+    let row_sep_str = row_sep.repeat(num_col_chars);
+    let board_str: String = Itertools::intersperse(board_row_strs.iter(), &row_sep_str).join("\n");
 
     write!(f, "{}", board_str)
   }
 }
 
 impl Board {
-  /// The tiles that are fixed to the board and cannot be moved or rotated
+  /// How many tiles there are along each side of the standard board
+  pub const STANDARD_SIZE: usize = 7;
+
+  /// The tiles that are fixed to the standard board and cannot be moved or rotated
   const FIXED_TILES: [(Location, Tile); 16] = [
     (
       Location(0, 0),
@@ -582,7 +592,7 @@ impl Board {
     ),
   ];
 
-  /// The tiles that are free to be placed or rotated
+  /// The tiles that are free to be placed or rotated on the standard board
   const FREE_TILES: [Tile; 34] = [
     Tile {
       marking: Some(TileMarking::Item(Item::Spider)),
@@ -656,40 +666,30 @@ impl Board {
     Tile::CORNER_RIGHT_UP,
   ];
 
-  pub fn locations() -> Box<dyn Iterator<Item = Location>> {
-    let mut l: Vec<_> = (0..7)
-      .permutations(2)
-      .chain((0..7).map(|i| vec![i, i]))
-      .collect();
-    l.sort_by_key(|a| match &a[..] {
-      &[x, y] => (y, x),
-      _ => (0, 0),
-    });
+  /// How many tiles there are along each side of the board
+  pub fn size(&self) -> usize {
+    self.size
+  }
 
-    Box::new(l.into_iter().filter_map(|a| match &a[..] {
-      &[x, y] => Some(Location(x, y)),
-      _ => None,
-    }))
+  /// Every location on the board, in rows from the top left
+  pub fn locations(&self) -> impl Iterator<Item = Location> {
+    locations(self.size)
   }
 
-  pub fn neighbors(&self, at: &Location) -> Result<Vec<Location>, LocationError> {
+  pub fn neighbors(&self, at: &Location) -> LabyrinthResult<Vec<Location>> {
     // Check location exists on board
     let here = Tile::from(self.placed.get(at).ok_or(at)?);
 
     // No neighbors off the edge of the board
     let up = if at.1 > 0 {
       let up_at = Location(at.0, at.1 - 1);
-      self
-        .placed
-        .get(&up_at)
-        .map(|tile| {
-          if here.path_up && Tile::from(tile).path_down {
-            Some(up_at)
-          } else {
-            None
-          }
-        })
-        .flatten()
+      self.placed.get(&up_at).and_then(|tile| {
+        if here.path_up && Tile::from(tile).path_down {
+          Some(up_at)
+        } else {
+          None
+        }
+      })
     } else {
       None
     };
@@ -697,53 +697,41 @@ impl Board {
     // No neighbors off the edge of the board
     let left = if at.0 > 0 {
       let left_at = Location(at.0 - 1, at.1);
-      self
-        .placed
-        .get(&left_at)
-        .map(|tile| {
-          if here.path_left && Tile::from(tile).path_right {
-            Some(left_at)
-          } else {
-            None
-          }
-        })
-        .flatten()
+      self.placed.get(&left_at).and_then(|tile| {
+        if here.path_left && Tile::from(tile).path_right {
+          Some(left_at)
+        } else {
+          None
+        }
+      })
     } else {
       None
     };
 
     // No neighbors off the edge of the board
-    let down = if at.1 + 1 < 6 {
+    let down = if at.1 + 1 < self.size {
       let down_at = Location(at.0, at.1 + 1);
-      self
-        .placed
-        .get(&down_at)
-        .map(|tile| {
-          if here.path_down && Tile::from(tile).path_up {
-            Some(down_at)
-          } else {
-            None
-          }
-        })
-        .flatten()
+      self.placed.get(&down_at).and_then(|tile| {
+        if here.path_down && Tile::from(tile).path_up {
+          Some(down_at)
+        } else {
+          None
+        }
+      })
     } else {
       None
     };
 
     // No neighbors off the edge of the board
-    let right = if at.0 + 1 < 6 {
+    let right = if at.0 + 1 < self.size {
       let right_at = Location(at.0 + 1, at.1);
-      self
-        .placed
-        .get(&right_at)
-        .map(|tile| {
-          if here.path_right && Tile::from(tile).path_left {
-            Some(right_at)
-          } else {
-            None
-          }
-        })
-        .flatten()
+      self.placed.get(&right_at).and_then(|tile| {
+        if here.path_right && Tile::from(tile).path_left {
+          Some(right_at)
+        } else {
+          None
+        }
+      })
     } else {
       None
     };
@@ -753,6 +741,60 @@ impl Board {
     Ok(neighbors)
   }
 
+  /// All locations that can be reached by following paths from a location, including itself.
+  /// An empty set is returned if the location is not on the board.
+  pub fn reachable_from(&self, from: &Location) -> HashSet<Location> {
+    if !self.placed.contains_key(from) {
+      return HashSet::new();
+    }
+
+    let mut reachable = HashSet::from([*from]);
+    let mut to_visit = vec![*from];
+
+    while let Some(at) = to_visit.pop() {
+      for neighbor_at in self.neighbors(&at).unwrap() {
+        if reachable.insert(neighbor_at) {
+          to_visit.push(neighbor_at);
+        }
+      }
+    }
+
+    reachable
+  }
+
+  /// The shortest route along the paths between two locations, including both ends.
+  /// Returns None if there is no route or either location is not on the board.
+  pub fn shortest_path(&self, from: &Location, to: &Location) -> Option<Vec<Location>> {
+    if !self.placed.contains_key(from) || !self.placed.contains_key(to) {
+      return None;
+    }
+
+    // Breadth first search, remembering which location each location was first reached from
+    let mut came_from: HashMap<Location, Location> = HashMap::from([(*from, *from)]);
+    let mut to_visit = VecDeque::from([*from]);
+
+    while let Some(at) = to_visit.pop_front() {
+      if at == *to {
+        let mut path = vec![at];
+        while path.last() != Some(from) {
+          path.push(came_from[path.last().unwrap()]);
+        }
+        path.reverse();
+
+        return Some(path);
+      }
+
+      for neighbor_at in self.neighbors(&at).unwrap() {
+        if let Entry::Vacant(entry) = came_from.entry(neighbor_at) {
+          entry.insert(at);
+          to_visit.push_back(neighbor_at);
+        }
+      }
+    }
+
+    None
+  }
+
   pub fn players(&self) -> HashMap<Location, Player> {
     self
       .placed
@@ -761,22 +803,26 @@ impl Board {
       .collect()
   }
 
-  pub fn move_player(&mut self, player: &Player, move_to: &Location) -> GenericResult<()> {
-    let players = self.players();
-
-    let current_location = *players
+  /// Where the player currently is on the board, if they are playing
+  pub fn player_location(&self, player: &Player) -> Option<Location> {
+    self
+      .placed
       .iter()
-      .filter_map(|(l, p)| if p == player { Some(*l) } else { None })
-      .collect::<Vec<_>>()
-      .first()
-      .ok_or(MoveError::new("Player not found on board"))?;
+      .find(|(_, tile)| tile.players.contains(player))
+      .map(|(location, _)| *location)
+  }
+
+  pub fn move_player(&mut self, player: &Player, move_to: &Location) -> LabyrinthResult<()> {
+    let current_location = self
+      .player_location(player)
+      .ok_or(LabyrinthError::PlayerNotOnBoard { player: *player })?;
 
     if self.graph.is_connected(&current_location, move_to)? {
       // Take player off previous tile
       self
         .placed
         .get_mut(&current_location)
-        .ok_or(LocationError::from(move_to))?
+        .ok_or(LabyrinthError::from(&current_location))?
         .players
         .remove(player);
 
@@ -784,85 +830,203 @@ impl Board {
       self
         .placed
         .get_mut(move_to)
-        .ok_or(LocationError::from(move_to))?
+        .ok_or(LabyrinthError::from(move_to))?
         .players
         .insert(*player);
       Ok(())
     } else {
-      Err(Box::new(MoveError::new("No path to destination")))
+      Err(LabyrinthError::NoPath {
+        from: current_location,
+        to: *move_to,
+      })
+    }
+  }
+
+  /// Put a player on a tile without checking there is a path to it, e.g. to undo a move
+  pub fn place_player(&mut self, player: &Player, at: &Location) -> LabyrinthResult<()> {
+    if !self.placed.contains_key(at) {
+      return Err(LabyrinthError::from(at));
+    }
+
+    for tile in self.placed.values_mut() {
+      tile.players.remove(player);
     }
+    self.placed.get_mut(at).unwrap().players.insert(*player);
+
+    Ok(())
   }
 
-  /// Check whether placing a tile with a certain rotation at a certain location is allowed
+  /// Check whether placing a tile with a certain rotation at a certain location is allowed on a
+  /// board `size` tiles across.
   /// No tile can be placed so that one of the openings leads off the board
-  fn tile_placement_ok(location: &Location, placed_tile: &PlacedTile) -> bool {
+  fn tile_placement_ok(size: usize, location: &Location, placed_tile: &PlacedTile) -> bool {
     let rotated_tile = Tile::from(placed_tile);
-    match location {
-      // top left corner
-      Location(0, 0) => !rotated_tile.path_up && !rotated_tile.path_left,
-      // top right corner
-      Location(6, 0) => !rotated_tile.path_up && !rotated_tile.path_right,
-      // bottom right corner
-      Location(6, 6) => !rotated_tile.path_down && !rotated_tile.path_right,
-      // bottom left corner
-      Location(0, 6) => !rotated_tile.path_down && !rotated_tile.path_left,
-      // bottom edge corner
-      Location(_, 6) => !rotated_tile.path_down,
-      // top edge corner
-      Location(_, 0) => !rotated_tile.path_up,
-      // left edge corner
-      Location(0, _) => !rotated_tile.path_left,
-      // right edge corner
-      Location(6, _) => !rotated_tile.path_right,
-      _ => true,
-    }
+    let last = size - 1;
+
+    // top edge, including the corners
+    !(location.1 == 0 && rotated_tile.path_up
+      // right edge
+      || location.0 == last && rotated_tile.path_right
+      // bottom edge
+      || location.1 == last && rotated_tile.path_down
+      // left edge
+      || location.0 == 0 && rotated_tile.path_left)
   }
 
   // Get the on the tile at the location, if there is one
-  pub fn item_at(&self, location: &Location) -> GenericResult<Option<Item>> {
+  pub fn item_at(&self, location: &Location) -> LabyrinthResult<Option<Item>> {
     if let Some(tile) = self.placed.get(location) {
       match tile.tile.marking {
         Some(TileMarking::Item(item)) => Ok(Some(item)),
         _ => Ok(None),
       }
     } else {
-      Err(Box::new(LocationError::from(location)))
+      Err(LabyrinthError::from(location))
     }
   }
 
-  /// Create a new board including the fixed tiles, with free tiles placed using the random number generator
-  pub fn new<R: Rng>(rng: &mut R, players: &HashSet<Player>) -> Board {
-    let fixed_tiles = Board::FIXED_TILES.clone().map(|(location, tile)| {
-      (
-        location,
-        PlacedTile {
-          tile,
-          rotation: Rotation::Zero,
-          players: tile
-            .marking
-            .map(|marking| match marking {
-              TileMarking::PlayerStart(Player::Player1) if players.contains(&Player::Player1) => {
-                HashSet::from([Player::Player1])
-              }
-              TileMarking::PlayerStart(Player::Player2) if players.contains(&Player::Player2) => {
-                HashSet::from([Player::Player2])
-              }
-              TileMarking::PlayerStart(Player::Player3) if players.contains(&Player::Player3) => {
-                HashSet::from([Player::Player3])
-              }
-              TileMarking::PlayerStart(Player::Player4) if players.contains(&Player::Player4) => {
-                HashSet::from([Player::Player4])
+  /// Every item on the board, including on the spare tile
+  pub fn items(&self) -> Vec<Item> {
+    let markings: Vec<Option<TileMarking>> = self
+      .placed
+      .values()
+      .map(|placed_tile| placed_tile.tile.marking)
+      .chain([self.spare.marking])
+      .collect();
+
+    Item::iter()
+      .filter(|item| markings.contains(&Some(TileMarking::Item(*item))))
+      .collect()
+  }
+
+  /// Check that the board is complete: every location has a tile, and no item is on more than
+  /// one tile, including the spare
+  pub fn validate(&self) -> LabyrinthResult<()> {
+    check_size(self.size)?;
+
+    if self.placed.len() != self.size * self.size {
+      return Err(LabyrinthError::InvalidBoard {
+        reason: format!(
+          "Expected {} placed tiles, found {}",
+          self.size * self.size,
+          self.placed.len()
+        ),
+      });
+    }
+
+    if let Some(missing) = self
+      .locations()
+      .find(|location| !self.placed.contains_key(location))
+    {
+      return Err(LabyrinthError::InvalidBoard {
+        reason: format!("No tile placed at {}", missing),
+      });
+    }
+
+    let items: Vec<Item> = self
+      .placed
+      .values()
+      .map(|placed_tile| &placed_tile.tile)
+      .chain([&self.spare])
+      .filter_map(|tile| match tile.marking {
+        Some(TileMarking::Item(item)) => Some(item),
+        _ => None,
+      })
+      .collect();
+
+    for item in Item::iter() {
+      let count = items.iter().filter(|found| **found == item).count();
+      if count > 1 {
+        return Err(LabyrinthError::InvalidBoard {
+          reason: format!(
+            "Expected item {} on at most one tile, found it on {}",
+            item, count
+          ),
+        });
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Check that the connected paths kept up to date as tiles are inserted are the same as those
+  /// found by starting again from scratch
+  pub fn check_graph(&self) -> LabyrinthResult<()> {
+    let rebuilt = BoardGraph::from(self);
+    let mut same_component: HashMap<i32, i32> = HashMap::new();
+    let mut rebuilt_component: HashMap<i32, i32> = HashMap::new();
+
+    for location in self.locations() {
+      let kept = self.graph.components.get(&location);
+      let fresh = rebuilt.components.get(&location);
+
+      let consistent = match (kept, fresh) {
+        (Some(kept), Some(fresh)) => {
+          *same_component.entry(*kept).or_insert(*fresh) == *fresh
+            && *rebuilt_component.entry(*fresh).or_insert(*kept) == *kept
+        }
+        _ => false,
+      };
+
+      if !consistent {
+        return Err(LabyrinthError::BrokenInvariant {
+          reason: format!(
+            "The paths joined to {} are not the same as when worked out from scratch",
+            location
+          ),
+        });
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Recalculate which tiles are connected from scratch, e.g. after the tiles have been loaded
+  pub fn rebuild_graph(&mut self) {
+    self.graph = BoardGraph::from(&*self);
+  }
+
+  /// Create a new board from a layout, with the fixed tiles in place and the free tiles placed using
+  /// the random number generator.
+  /// Fails if a board cannot be made from the layout, or it has no start for one of the players.
+  pub fn new<R: Rng>(
+    rng: &mut R,
+    players: &HashSet<Player>,
+    layout: &BoardLayout,
+  ) -> LabyrinthResult<Board> {
+    layout.validate()?;
+
+    let starts = layout.starts();
+    if let Some(player) = players.iter().find(|player| !starts.contains(player)) {
+      return Err(LabyrinthError::InvalidLayout {
+        reason: format!("There is no start for {:?}", player),
+      });
+    }
+
+    let fixed_tiles: Vec<(Location, PlacedTile)> = layout
+      .fixed_tiles
+      .iter()
+      .map(|(location, tile)| {
+        (
+          *location,
+          PlacedTile {
+            tile: *tile,
+            rotation: Rotation::Zero,
+            players: match tile.marking {
+              Some(TileMarking::PlayerStart(player)) if players.contains(&player) => {
+                HashSet::from([player])
               }
               _ => HashSet::new(),
-            })
-            .unwrap_or(HashSet::new()),
-        },
-      )
-    });
+            },
+          },
+        )
+      })
+      .collect();
 
-    let mut free_tiles: Vec<PlacedTile> = Board::FREE_TILES
-      .clone()
-      .into_iter()
+    let mut free_tiles: Vec<PlacedTile> = layout
+      .free_tiles
+      .iter()
+      .copied()
       .map(|tile| PlacedTile {
         tile,
         rotation: rng.gen(),
@@ -870,7 +1034,7 @@ impl Board {
       })
       .collect();
 
-    let mut free_locations: Vec<Location> = Board::locations()
+    let mut free_locations: Vec<Location> = locations(layout.size)
       .filter(|location| {
         !fixed_tiles
           .iter()
@@ -879,9 +1043,6 @@ impl Board {
       })
       .collect();
 
-    // There should always be 1 more free tile than free location
-    assert_eq!(free_locations.len(), free_tiles.len() - 1);
-
     free_tiles.shuffle(rng);
     free_locations.shuffle(rng);
 
@@ -889,61 +1050,69 @@ impl Board {
     let mut placed_tiles: Vec<_> = free_locations.into_iter().zip(free_tiles).collect();
 
     // Rotate any tiles that would have an invalid placement until placement okay
-    for mut placed_tile in &mut placed_tiles {
-      while !Board::tile_placement_ok(&placed_tile.0, &placed_tile.1) {
+    for placed_tile in &mut placed_tiles {
+      while !Board::tile_placement_ok(layout.size, &placed_tile.0, &placed_tile.1) {
         (placed_tile.1).rotation = rng.gen();
       }
     }
 
+    Ok(Board::from_placed(
+      layout.size,
+      fixed_tiles.into_iter().chain(placed_tiles).collect(),
+      extra_tile,
+      None,
+    ))
+  }
+
+  /// Create a board `size` tiles across from tiles that have already been placed, e.g. to show a
+  /// snapshot of a game
+  pub fn from_placed(
+    size: usize,
+    placed: HashMap<Location, PlacedTile>,
+    spare: Tile,
+    forbidden_insertion: Option<Location>,
+  ) -> Board {
     let mut board = Board {
-      placed: fixed_tiles.into_iter().chain(placed_tiles).collect(),
-      spare: extra_tile,
+      placed,
+      spare,
+      size,
       graph: BoardGraph::empty(),
+      last_push_out: forbidden_insertion,
     };
-    board.graph = BoardGraph::from(&board);
+    board.rebuild_graph();
 
     board
   }
 
-  /// Generic helper function for rotating a row or column
+  /// Generic helper function for rotating a row or column.
+  /// Returns the rotation of the tile that was pushed off the board.
   fn rotate_common(
     &mut self,
     idx: usize,
-    tile_rotation: Rotation,
+    mut to_push_in: PlacedTile,
     reverse: bool,
     idx_is_x: bool,
-  ) -> Result<(), LocationError> {
+  ) -> LabyrinthResult<Rotation> {
+    let last = self.size - 1;
+
     let push_in_at = match (reverse, idx_is_x) {
       (false, false) => Location(0, idx),
       (false, true) => Location(idx, 0),
-      (true, false) => Location(6, idx),
-      (true, true) => Location(idx, 6),
+      (true, false) => Location(last, idx),
+      (true, true) => Location(idx, last),
     };
 
     let push_out_at = match (reverse, idx_is_x) {
-      (false, false) => Location(6, idx),
-      (false, true) => Location(idx, 6),
+      (false, false) => Location(last, idx),
+      (false, true) => Location(idx, last),
       (true, false) => Location(0, idx),
       (true, true) => Location(idx, 0),
     };
 
-    let mut to_push_in = PlacedTile {
-      tile: self.spare,
-      rotation: tile_rotation,
-      players: HashSet::new(),
-    };
-
-    // Ensure rotating the spare tile in won't break the board
-    if !Board::tile_placement_ok(&push_in_at, &to_push_in) {
-      return Err(LocationError::new(
-        "Tile cannot be inserted here with this rotation",
-      ));
-    }
-
     let pushed_out = self
       .placed
       .remove(&push_out_at)
-      .ok_or(LocationError::from(&push_out_at))?;
+      .ok_or(LabyrinthError::from(&push_out_at))?;
 
     // If a player is pushed off the board then they are moved to the newly inserted tile
     to_push_in.players = pushed_out.players;
@@ -951,9 +1120,9 @@ impl Board {
     let mut moving_tile = self.placed.remove(&push_in_at);
 
     let iter: Box<dyn Iterator<Item = usize>> = if reverse {
-      Box::new((0..6).rev())
+      Box::new((0..last).rev())
     } else {
-      Box::new(1..7)
+      Box::new(1..self.size)
     };
 
     for i in iter {
@@ -967,107 +1136,728 @@ impl Board {
 
     self.placed.insert(push_in_at, to_push_in);
     self.spare = pushed_out.tile;
+    self.last_push_out = Some(push_out_at);
 
-    Ok(())
+    let mut graph = std::mem::take(&mut self.graph);
+    graph.update_line(self, idx, idx_is_x);
+    self.graph = graph;
+
+    Ok(pushed_out.rotation)
   }
 
-  /// Rotate row y left, replacing the rightmost tile with the spare tile
-  fn rotate_left(&mut self, y: usize, tile_rotation: Rotation) -> Result<(), LocationError> {
-    self.rotate_common(y, tile_rotation, true, false)
+  /// The row or column that inserting a tile at a location would rotate, as
+  /// `(index, reverse, index_is_x)`. Returns None if a tile cannot be inserted there.
+  fn line_of(&self, insert_at: &Location) -> Option<(usize, bool, bool)> {
+    let last = self.size - 1;
+
+    match *insert_at {
+      // Rotate column x down, replacing the topmost tile with the spare tile
+      Location(x, 0) if x % 2 == 1 && x < last => Some((x, false, true)),
+      // Rotate row y left, replacing the rightmost tile with the spare tile
+      Location(x, y) if x == last && y % 2 == 1 && y < last => Some((y, true, false)),
+      // Rotate column x up, replacing the bottommost tile with the spare tile
+      Location(x, y) if y == last && x % 2 == 1 && x < last => Some((x, true, true)),
+      // Rotate row y right, replacing the leftmost tile with the spare tile
+      Location(0, y) if y % 2 == 1 && y < last => Some((y, false, false)),
+      _ => None,
+    }
   }
 
-  /// Rotate row y right, replacing the leftmost tile with the spare tile
-  fn rotate_right(&mut self, y: usize, tile_rotation: Rotation) -> Result<(), LocationError> {
-    self.rotate_common(y, tile_rotation, false, false)
+  /// All locations the spare tile can be inserted at, ignoring whether it would reverse the
+  /// previous insertion: both ends of every odd row and column
+  pub fn insertion_locations(&self) -> Vec<Location> {
+    let last = self.size - 1;
+
+    (1..last)
+      .step_by(2)
+      .flat_map(|i| {
+        [
+          Location(i, 0),
+          Location(last, i),
+          Location(i, last),
+          Location(0, i),
+        ]
+      })
+      .collect()
   }
 
-  /// Rotate column x up, replacing the bottommost tile with the spare tile
-  fn rotate_up(&mut self, x: usize, tile_rotation: Rotation) -> Result<(), LocationError> {
-    self.rotate_common(x, tile_rotation, true, true)
+  /// Every insertion location and rotation of the spare tile allowed this turn.
+  /// Rotations that would leave the tile looking the same are only included once.
+  pub fn legal_insertions(&self) -> Vec<(Location, Rotation)> {
+    self
+      .insertion_locations()
+      .into_iter()
+      .filter(|location| self.last_push_out != Some(*location))
+      .flat_map(|location| {
+        let mut rotated_tiles: Vec<Tile> = Vec::new();
+
+        Rotation::iter()
+          .filter(|rotation| {
+            let placed_tile = PlacedTile {
+              tile: self.spare,
+              rotation: *rotation,
+              players: HashSet::new(),
+            };
+            let rotated_tile = Tile::from(&placed_tile);
+
+            if !Board::tile_placement_ok(self.size, &location, &placed_tile)
+              || rotated_tiles.contains(&rotated_tile)
+            {
+              return false;
+            }
+
+            rotated_tiles.push(rotated_tile);
+            true
+          })
+          .map(move |rotation| (location, rotation))
+          .collect::<Vec<_>>()
+      })
+      .collect()
   }
 
-  /// Rotate column x down, replacing the topmost tile with the spare tile
-  fn rotate_down(&mut self, x: usize, tile_rotation: Rotation) -> Result<(), LocationError> {
-    self.rotate_common(x, tile_rotation, false, true)
+  /// The insertion location that is not allowed this turn, as it would push back the tile that
+  /// was just pushed out and undo the previous insertion
+  pub fn forbidden_insertion(&self) -> Option<Location> {
+    self.last_push_out
   }
 
   /// Try to insert the extra tile at a given location, sliding all the tiles in the row/column by 1.
   /// Inserting a tile pushes the tile opposite off the board, which becomes the new extra tile.
-  /// Returns Ok(()) if insertion was possible, and Err(()) if not.
-  /// Valid insertion locations are:
+  /// Returns Ok(_) if insertion was possible, describing how to undo it, and Err(_) if not.
+  /// Valid insertion locations are both ends of every odd row and column, e.g. on the standard
+  /// board:
   /// (1,0), (3,0), (5,0), (6,1), (6,3), (6,5), (1,6), (3,6), (5,6), (0,1), (0,3), (0,5),
+  /// except for the location the previous tile was pushed out from.
   pub fn insert_spare(
     &mut self,
     insert_at: Location,
     rotation: Rotation,
-  ) -> Result<(), LocationError> {
-    match insert_at {
-      Location(x, 0) if x % 2 == 1 => self.rotate_down(x, rotation),
-      Location(6, y) if y % 2 == 1 => self.rotate_left(y, rotation),
-      Location(x, 6) if x % 2 == 1 => self.rotate_up(x, rotation),
-      Location(0, y) if y % 2 == 1 => self.rotate_right(y, rotation),
-      _ => Err(LocationError::new(&format!(
-        "Cannot insert a tile at location {}",
-        insert_at,
-      ))),
+  ) -> LabyrinthResult<Insertion> {
+    if self.last_push_out == Some(insert_at) {
+      return Err(LabyrinthError::ReversedInsertion {
+        location: insert_at,
+      });
     }
+
+    let (idx, reverse, idx_is_x) =
+      self
+        .line_of(&insert_at)
+        .ok_or(LabyrinthError::InvalidInsertion {
+          location: insert_at,
+        })?;
+
+    let to_push_in = PlacedTile {
+      tile: self.spare,
+      rotation,
+      players: HashSet::new(),
+    };
+
+    // Ensure rotating the spare tile in won't break the board
+    if !Board::tile_placement_ok(self.size, &insert_at, &to_push_in) {
+      return Err(LabyrinthError::InvalidRotation {
+        location: insert_at,
+        rotation,
+      });
+    }
+
+    let previous_push_out = self.last_push_out;
+    let pushed_out_rotation = self.rotate_common(idx, to_push_in, reverse, idx_is_x)?;
+
+    Ok(Insertion {
+      insert_at,
+      pushed_out_rotation,
+      previous_push_out,
+    })
   }
+
+  /// Reverse the most recent insertion of the spare tile, pushing the tile that was pushed out
+  /// back in with its original rotation, along with any players that were carried across
+  pub fn undo_insertion(&mut self, insertion: &Insertion) {
+    let (idx, reverse, idx_is_x) = self.line_of(&insertion.insert_at).unwrap();
+
+    let to_push_in = PlacedTile {
+      tile: self.spare,
+      rotation: insertion.pushed_out_rotation,
+      players: HashSet::new(),
+    };
+
+    self
+      .rotate_common(idx, to_push_in, !reverse, idx_is_x)
+      .unwrap();
+    self.last_push_out = insertion.previous_push_out;
+  }
+}
+
+/// The tiles a board is made from: those fixed in place, and those shuffled onto the rest of the
+/// board with one left over as the spare.
+/// Layouts other than the standard ones can be loaded from TOML or JSON files.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct BoardLayout {
+  /// How many tiles there are along each side of the board
+  pub size: usize,
+  #[cfg_attr(feature = "serde", serde(with = "fixed_tiles_serde"))]
+  pub fixed_tiles: Vec<(Location, Tile)>,
+  #[cfg_attr(feature = "serde", serde(with = "free_tiles_serde"))]
+  pub free_tiles: Vec<Tile>,
 }
 
+impl BoardLayout {
+  /// The standard board, or one like it of another size.
+  /// Other sizes have a tile fixed at every location where both coordinates are even, like the
+  /// standard board: the players' starts in the corners and T-junctions everywhere else, with their
+  /// closed side facing the nearest edge. Their free tiles have the same mix of shapes as the
+  /// standard board's. Items go on the fixed tiles first, then on the free corners and T-junctions,
+  /// until either the items or the tiles run out.
+  pub fn standard(size: usize) -> LabyrinthResult<BoardLayout> {
+    check_size(size)?;
+
+    if size == Board::STANDARD_SIZE {
+      return Ok(BoardLayout {
+        size,
+        fixed_tiles: Board::FIXED_TILES.to_vec(),
+        free_tiles: Board::FREE_TILES.to_vec(),
+      });
+    }
+
+    let mut items = Item::iter().map(TileMarking::Item);
+    let last = size - 1;
+
+    let fixed_tiles: Vec<(Location, Tile)> = locations(size)
+      .filter(|location| location.0 % 2 == 0 && location.1 % 2 == 0)
+      .map(|location| {
+        let (marking, shape) = match location {
+          Location(0, 0) => (
+            Some(TileMarking::PlayerStart(Player::Player1)),
+            Tile::CORNER_RIGHT_DOWN,
+          ),
+          Location(x, 0) if x == last => (
+            Some(TileMarking::PlayerStart(Player::Player2)),
+            Tile::CORNER_LEFT_DOWN,
+          ),
+          Location(0, y) if y == last => (
+            Some(TileMarking::PlayerStart(Player::Player3)),
+            Tile::CORNER_RIGHT_UP,
+          ),
+          Location(x, y) if x == last && y == last => (
+            Some(TileMarking::PlayerStart(Player::Player4)),
+            Tile::CORNER_LEFT_UP,
+          ),
+          _ => (items.next(), fixed_tee(size, &location)),
+        };
+
+        (location, Tile { marking, ..shape })
+      })
+      .collect();
+
+    // Half of the standard board's free tiles: corners and T-junctions with items on, then plain
+    // lines and corners
+    let mix: Vec<(Tile, bool)> = [(Tile::CORNER_RIGHT_DOWN, true), (Tile::TEE_UP, true)]
+      .repeat(3)
+      .into_iter()
+      .chain([(Tile::LINE_VERTICAL, false); 6])
+      .chain([(Tile::CORNER_RIGHT_UP, false); 5])
+      .collect();
+
+    let free_tiles = mix
+      .into_iter()
+      .cycle()
+      .take(size * size - fixed_tiles.len() + 1)
+      .map(|(shape, has_item)| Tile {
+        marking: if has_item { items.next() } else { None },
+        ..shape
+      })
+      .collect();
+
+    Ok(BoardLayout {
+      size,
+      fixed_tiles,
+      free_tiles,
+    })
+  }
+
+  /// Check that a board can be made from the layout:
+  /// - tiles are only fixed where inserting the spare never moves them, with no path leading off
+  ///   the board
+  /// - there is a free tile for every other location, plus one for the spare
+  /// - every free tile can be turned to fit wherever it is shuffled to
+  /// - each player's start is on at most one tile, which is fixed
+  /// - each item is on at most one tile, so every item dealt can be found in exactly one place
+  pub fn validate(&self) -> LabyrinthResult<()> {
+    check_size(self.size)?;
+
+    let invalid = |reason: String| Err(LabyrinthError::InvalidLayout { reason });
+
+    let mut fixed_locations = HashSet::new();
+    for (location, tile) in &self.fixed_tiles {
+      if location.0 >= self.size || location.1 >= self.size {
+        return invalid(format!("The tile fixed at {} is off the board", location));
+      }
+      if !location.0.is_multiple_of(2) || !location.1.is_multiple_of(2) {
+        return invalid(format!(
+          "No tile can be fixed at {}, as inserting the spare tile moves it",
+          location
+        ));
+      }
+      if !fixed_locations.insert(*location) {
+        return invalid(format!("More than one tile is fixed at {}", location));
+      }
+      if !Board::tile_placement_ok(self.size, location, &PlacedTile::from(tile)) {
+        return invalid(format!(
+          "The tile fixed at {} has a path leading off the board",
+          location
+        ));
+      }
+    }
+
+    let free_locations: Vec<Location> = locations(self.size)
+      .filter(|location| !fixed_locations.contains(location))
+      .collect();
+    if self.free_tiles.len() != free_locations.len() + 1 {
+      return invalid(format!(
+        "Expected {} free tiles, one for each of the {} locations without a fixed tile and one \
+         spare, found {}",
+        free_locations.len() + 1,
+        free_locations.len(),
+        self.free_tiles.len()
+      ));
+    }
+
+    for (number, tile) in self.free_tiles.iter().enumerate() {
+      let fits = |location: &Location| {
+        Rotation::iter().any(|rotation| {
+          let placed_tile = PlacedTile {
+            tile: *tile,
+            rotation,
+            players: HashSet::new(),
+          };
+          Board::tile_placement_ok(self.size, location, &placed_tile)
+        })
+      };
+
+      if let Some(location) = free_locations.iter().find(|location| !fits(location)) {
+        return invalid(format!(
+          "Free tile {} cannot be turned to fit at {}",
+          number + 1,
+          location
+        ));
+      }
+    }
+
+    let fixed_markings: Vec<TileMarking> = self
+      .fixed_tiles
+      .iter()
+      .filter_map(|(_, tile)| tile.marking)
+      .collect();
+    let free_markings: Vec<TileMarking> = self
+      .free_tiles
+      .iter()
+      .filter_map(|tile| tile.marking)
+      .collect();
+
+    for player in Player::iter() {
+      let start = TileMarking::PlayerStart(player);
+      if free_markings.contains(&start) {
+        return invalid(format!(
+          "The start for {:?} must be on a fixed tile",
+          player
+        ));
+      }
+      let count = fixed_markings
+        .iter()
+        .filter(|marking| **marking == start)
+        .count();
+      if count > 1 {
+        return invalid(format!(
+          "Expected the start for {:?} on at most one tile, found it on {}",
+          player, count
+        ));
+      }
+    }
+
+    for item in Item::iter() {
+      let count = fixed_markings
+        .iter()
+        .chain(&free_markings)
+        .filter(|marking| **marking == TileMarking::Item(item))
+        .count();
+      if count > 1 {
+        return invalid(format!(
+          "Expected item {} on at most one tile, found it on {}",
+          item, count
+        ));
+      }
+    }
+
+    Ok(())
+  }
+
+  /// The players with a start on the layout, who are the only ones who can play on it
+  pub fn starts(&self) -> Vec<Player> {
+    self
+      .fixed_tiles
+      .iter()
+      .filter_map(|(_, tile)| match tile.marking {
+        Some(TileMarking::PlayerStart(player)) => Some(player),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Every item on the layout's tiles
+  pub fn items(&self) -> Vec<Item> {
+    let markings: Vec<Option<TileMarking>> = self
+      .fixed_tiles
+      .iter()
+      .map(|(_, tile)| tile)
+      .chain(&self.free_tiles)
+      .map(|tile| tile.marking)
+      .collect();
+
+    Item::iter()
+      .filter(|item| markings.contains(&Some(TileMarking::Item(*item))))
+      .collect()
+  }
+}
+
+#[cfg(feature = "serde")]
+impl BoardLayout {
+  /// Load a layout from TOML, checking that a board can be made from it
+  pub fn load_toml(toml: &str) -> LabyrinthResult<Self> {
+    let layout: BoardLayout =
+      toml::from_str(toml).map_err(|error| LabyrinthError::InvalidLayout {
+        reason: error.to_string(),
+      })?;
+    layout.validate()?;
+
+    Ok(layout)
+  }
+
+  /// Load a layout from JSON, checking that a board can be made from it
+  pub fn load_json(json: &str) -> LabyrinthResult<Self> {
+    let layout: BoardLayout =
+      serde_json::from_str(json).map_err(|error| LabyrinthError::InvalidLayout {
+        reason: error.to_string(),
+      })?;
+    layout.validate()?;
+
+    Ok(layout)
+  }
+
+  /// Save the layout as TOML, e.g. as a starting point for designing a new one
+  pub fn save_toml(&self) -> LabyrinthResult<String> {
+    toml::to_string(self).map_err(|error| LabyrinthError::InvalidLayout {
+      reason: error.to_string(),
+    })
+  }
+
+  /// Save the layout as JSON
+  pub fn save_json(&self) -> LabyrinthResult<String> {
+    serde_json::to_string_pretty(self).map_err(|error| LabyrinthError::InvalidLayout {
+      reason: error.to_string(),
+    })
+  }
+}
+
+/// The T-junction fixed at a location, with its closed side facing the nearest edge of the board.
+/// Where two edges are as near, the closed sides go round in a pinwheel like on the standard board.
+fn fixed_tee(size: usize, at: &Location) -> Tile {
+  let last = size - 1;
+  let centre = last / 2;
+
+  // Each side's distance from the edge, and the T-junction closed on that side
+  let left = (at.0, Tile::TEE_RIGHT);
+  let up = (at.1, Tile::TEE_DOWN);
+  let right = (last - at.0, Tile::TEE_LEFT);
+  let down = (last - at.1, Tile::TEE_UP);
+
+  // The side that wins a tie comes first
+  let sides = match (at.0 <= centre, at.1 <= centre) {
+    (true, true) => [left, up, right, down],
+    (false, true) => [up, right, down, left],
+    (false, false) => [right, down, left, up],
+    (true, false) => [down, left, up, right],
+  };
+
+  sides
+    .into_iter()
+    .min_by_key(|(distance, _)| *distance)
+    .map(|(_, tile)| tile)
+    .unwrap()
+}
+
+/// Check that a board can be `size` tiles across: an odd number, so that every other row and column
+/// can be moved, and at least 5
+fn check_size(size: usize) -> LabyrinthResult<()> {
+  if size < 5 || size.is_multiple_of(2) {
+    return Err(LabyrinthError::InvalidBoard {
+      reason: format!(
+        "The board must be an odd number of tiles across, at least 5, not {}",
+        size
+      ),
+    });
+  }
+
+  Ok(())
+}
+
+/// Every location on a board `size` tiles across, in rows from the top left
+fn locations(size: usize) -> impl Iterator<Item = Location> {
+  (0..size).flat_map(move |y| (0..size).map(move |x| Location(x, y)))
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn standard_size() -> usize {
+  Board::STANDARD_SIZE
+}
+
+/// How the spare tile was inserted, with everything needed to undo the insertion
+#[derive(Debug, Clone)]
+pub struct Insertion {
+  pub insert_at: Location,
+  /// The rotation of the tile that was pushed off the board and became the spare tile
+  pub pushed_out_rotation: Rotation,
+  previous_push_out: Option<Location>,
+}
+
+/// Placed tiles are saved as a list, as locations cannot be used as keys in formats such as JSON
+#[cfg(feature = "serde")]
+pub(crate) mod placed_serde {
+  use super::{Location, PlacedTile};
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+  use std::collections::HashMap;
+
+  pub fn serialize<S: Serializer>(
+    placed: &HashMap<Location, PlacedTile>,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    let mut tiles: Vec<(&Location, &PlacedTile)> = placed.iter().collect();
+    tiles.sort_by_key(|(location, _)| (location.1, location.0));
+    tiles.serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<HashMap<Location, PlacedTile>, D::Error> {
+    let tiles: Vec<(Location, PlacedTile)> = Vec::deserialize(deserializer)?;
+    Ok(tiles.into_iter().collect())
+  }
+}
+
+/// Fixed tiles are saved in layouts with their location alongside the rest of the tile, leaving out
+/// the paths that are closed, e.g. in TOML
+/// `{ at = [0, 0], marking = { PlayerStart = "Player1" }, path_right = true, path_down = true }`
+#[cfg(feature = "serde")]
+mod fixed_tiles_serde {
+  use super::{Location, Tile, TileMarking};
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+  #[derive(Serialize, Deserialize)]
+  #[serde(deny_unknown_fields)]
+  struct FixedTile {
+    at: Location,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    marking: Option<TileMarking>,
+    #[serde(default, skip_serializing_if = "is_closed")]
+    path_up: bool,
+    #[serde(default, skip_serializing_if = "is_closed")]
+    path_right: bool,
+    #[serde(default, skip_serializing_if = "is_closed")]
+    path_down: bool,
+    #[serde(default, skip_serializing_if = "is_closed")]
+    path_left: bool,
+  }
+
+  fn is_closed(path: &bool) -> bool {
+    !path
+  }
+
+  pub fn serialize<S: Serializer>(
+    fixed_tiles: &[(Location, Tile)],
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    let tiles: Vec<FixedTile> = fixed_tiles
+      .iter()
+      .map(|(at, tile)| FixedTile {
+        at: *at,
+        marking: tile.marking,
+        path_up: tile.path_up,
+        path_right: tile.path_right,
+        path_down: tile.path_down,
+        path_left: tile.path_left,
+      })
+      .collect();
+    tiles.serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Vec<(Location, Tile)>, D::Error> {
+    let tiles: Vec<FixedTile> = Vec::deserialize(deserializer)?;
+    Ok(
+      tiles
+        .into_iter()
+        .map(|fixed| {
+          let tile = Tile {
+            marking: fixed.marking,
+            path_up: fixed.path_up,
+            path_right: fixed.path_right,
+            path_down: fixed.path_down,
+            path_left: fixed.path_left,
+          };
+          (fixed.at, tile)
+        })
+        .collect(),
+    )
+  }
+}
+
+/// Free tiles are saved in layouts as runs of the same tile, leaving out the paths that are
+/// closed, e.g. in TOML `{ count = 6, path_up = true, path_down = true }` for six straight lines.
+/// The count can be left out for a single tile.
+#[cfg(feature = "serde")]
+mod free_tiles_serde {
+  use super::{Tile, TileMarking};
+  use itertools::Itertools;
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+  use std::iter;
+
+  #[derive(Serialize, Deserialize)]
+  #[serde(deny_unknown_fields)]
+  struct FreeTiles {
+    #[serde(default = "one")]
+    count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    marking: Option<TileMarking>,
+    #[serde(default, skip_serializing_if = "is_closed")]
+    path_up: bool,
+    #[serde(default, skip_serializing_if = "is_closed")]
+    path_right: bool,
+    #[serde(default, skip_serializing_if = "is_closed")]
+    path_down: bool,
+    #[serde(default, skip_serializing_if = "is_closed")]
+    path_left: bool,
+  }
+
+  fn one() -> usize {
+    1
+  }
+
+  fn is_closed(path: &bool) -> bool {
+    !path
+  }
+
+  pub fn serialize<S: Serializer>(free_tiles: &[Tile], serializer: S) -> Result<S::Ok, S::Error> {
+    let runs: Vec<FreeTiles> = free_tiles
+      .iter()
+      .dedup_with_count()
+      .map(|(count, tile)| FreeTiles {
+        count,
+        marking: tile.marking,
+        path_up: tile.path_up,
+        path_right: tile.path_right,
+        path_down: tile.path_down,
+        path_left: tile.path_left,
+      })
+      .collect();
+    runs.serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Tile>, D::Error> {
+    let runs: Vec<FreeTiles> = Vec::deserialize(deserializer)?;
+    Ok(
+      runs
+        .into_iter()
+        .flat_map(|run| {
+          let tile = Tile {
+            marking: run.marking,
+            path_up: run.path_up,
+            path_right: run.path_right,
+            path_down: run.path_down,
+            path_left: run.path_left,
+          };
+          iter::repeat_n(tile, run.count)
+        })
+        .collect(),
+    )
+  }
+}
+
+/// Connected components of the board's paths, used to check whether one tile can be reached
+/// from another. Kept up to date as tiles are inserted by relabelling only the components that
+/// the shifted row or column could have touched.
+#[derive(Default, Clone)]
 struct BoardGraph {
   components: HashMap<Location, i32>,
+  next_component_id: i32,
 }
 
 impl BoardGraph {
   pub fn empty() -> BoardGraph {
-    BoardGraph {
-      components: HashMap::new(),
-    }
+    BoardGraph::default()
   }
-}
 
-impl From<&Board> for BoardGraph {
-  fn from(board: &Board) -> BoardGraph {
-    /// Depth first search based component labeling utility function
-    fn dfs_util(
-      board: &Board,
-      at: &Location,
-      visited: &mut HashMap<Location, bool>,
-      graph: &mut BoardGraph,
-      component_id: i32,
-    ) {
-      if !visited.get(at).unwrap_or(&false) {
-        visited.insert(*at, true);
-        graph.components.insert(*at, component_id);
-        for neighbor_at in board.neighbors(at).unwrap() {
-          dfs_util(board, &neighbor_at, visited, graph, component_id);
+  /// Label every location reachable from `start` with a new component id
+  fn label_component(&mut self, board: &Board, start: &Location) {
+    let component_id = self.next_component_id;
+    self.next_component_id += 1;
+
+    let mut to_visit = vec![*start];
+    self.components.insert(*start, component_id);
+
+    while let Some(at) = to_visit.pop() {
+      for neighbor_at in board.neighbors(&at).unwrap() {
+        if let Entry::Vacant(entry) = self.components.entry(neighbor_at) {
+          entry.insert(component_id);
+          to_visit.push(neighbor_at);
         }
       }
     }
+  }
 
-    let mut graph = BoardGraph {
-      components: HashMap::new(),
-    };
-
-    let mut current_component_id = -1;
-    let locations: Vec<Location> = board.placed.iter().map(|(location, _)| *location).collect();
+  /// Update the components after the row or column `idx` has been shifted.
+  /// Only paths into or within the shifted line can have changed, so only components containing
+  /// a tile in the line or either of the lines beside it need relabelling.
+  fn update_line(&mut self, board: &Board, idx: usize, idx_is_x: bool) {
+    let touched: HashSet<i32> = (idx.saturating_sub(1)..=idx + 1)
+      .flat_map(|line| {
+        (0..board.size).map(move |i| {
+          if idx_is_x {
+            Location(line, i)
+          } else {
+            Location(i, line)
+          }
+        })
+      })
+      .filter_map(|location| self.components.get(&location).copied())
+      .collect();
 
-    // Initially all locations are unvisited
-    let mut visited: HashMap<Location, bool> = locations
+    let to_relabel: Vec<Location> = self
+      .components
       .iter()
-      .map(|location| (*location, false))
+      .filter(|(_, component_id)| touched.contains(component_id))
+      .map(|(location, _)| *location)
       .collect();
 
-    for location in locations {
-      if !visited.get(&location).unwrap() {
-        current_component_id += 1;
-        dfs_util(
-          board,
-          &location,
-          &mut visited,
-          &mut graph,
-          current_component_id,
-        )
+    for location in &to_relabel {
+      self.components.remove(location);
+    }
+
+    for location in &to_relabel {
+      if !self.components.contains_key(location) {
+        self.label_component(board, location);
+      }
+    }
+  }
+}
+
+impl From<&Board> for BoardGraph {
+  fn from(board: &Board) -> BoardGraph {
+    let mut graph = BoardGraph::empty();
+
+    for location in board.placed.keys() {
+      if !graph.components.contains_key(location) {
+        graph.label_component(board, location);
       }
     }
 
@@ -1076,20 +1866,167 @@ impl From<&Board> for BoardGraph {
 }
 
 impl BoardGraph {
-  pub fn is_connected(
-    &self,
-    location1: &Location,
-    location2: &Location,
-  ) -> Result<bool, LocationError> {
+  pub fn is_connected(&self, location1: &Location, location2: &Location) -> LabyrinthResult<bool> {
     Ok(
       self
         .components
         .get(location1)
-        .ok_or(LocationError::from(location1))?
+        .ok_or(LabyrinthError::from(location1))?
         == self
           .components
           .get(location2)
-          .ok_or(LocationError::from(location2))?,
+          .ok_or(LabyrinthError::from(location2))?,
     )
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::seq::SliceRandom;
+  use rand::SeedableRng;
+  use rand_chacha::ChaCha8Rng;
+
+  /// The paths kept up to date by `update_line` as tiles are inserted and taken back must always
+  /// join the same tiles as a search from scratch
+  #[test]
+  fn connected_paths_match_a_search_from_scratch_after_random_insertions() {
+    let players = HashSet::from([Player::Player1, Player::Player2]);
+
+    for size in (5..=13).step_by(2) {
+      let mut rng = ChaCha8Rng::seed_from_u64(size as u64);
+      let layout = BoardLayout::standard(size).unwrap();
+      let mut board = Board::new(&mut rng, &players, &layout).unwrap();
+      let locations: Vec<Location> = board.locations().collect();
+      let mut insertions = Vec::new();
+
+      for _ in 0..2000 {
+        // Sometimes take back the last insertion, which relabels the line again
+        if !insertions.is_empty() && rng.gen_ratio(1, 4) {
+          board.undo_insertion(&insertions.pop().unwrap());
+        } else {
+          let (insert_at, rotation) = *board.legal_insertions().choose(&mut rng).unwrap();
+          insertions.push(board.insert_spare(insert_at, rotation).unwrap());
+        }
+
+        board.check_graph().unwrap();
+        let from = *locations.choose(&mut rng).unwrap();
+        let reachable = board.reachable_from(&from);
+        for to in &locations {
+          assert_eq!(
+            board.graph.is_connected(&from, to).unwrap(),
+            reachable.contains(to),
+            "size {}, from {} to {}",
+            size,
+            from,
+            to
+          );
+        }
+      }
+    }
+  }
+
+  /// A board 5 tiles across where every column is a separate straight path, except for the middle
+  /// tile which is turned across and so joins nothing
+  fn columns_with_a_closed_middle() -> Board {
+    let placed = (0..5)
+      .flat_map(|x| (0..5).map(move |y| Location(x, y)))
+      .map(|location| {
+        let mut placed = PlacedTile::from(&Tile::LINE_VERTICAL);
+        if location == Location(2, 2) {
+          placed.rotation = Rotation::Clockwise90;
+        }
+        (location, placed)
+      })
+      .collect();
+
+    Board::from_placed(5, placed, Tile::LINE_VERTICAL, None)
+  }
+
+  #[test]
+  fn an_enclosed_tile_reaches_only_itself() {
+    let board = columns_with_a_closed_middle();
+
+    assert_eq!(
+      board.reachable_from(&Location(2, 2)),
+      HashSet::from([Location(2, 2)])
+    );
+    assert_eq!(
+      board.reachable_from(&Location(2, 0)),
+      HashSet::from([Location(2, 0), Location(2, 1)])
+    );
+  }
+
+  #[test]
+  fn there_is_no_path_to_an_unreachable_tile() {
+    let board = columns_with_a_closed_middle();
+
+    assert_eq!(board.shortest_path(&Location(0, 0), &Location(1, 0)), None);
+    assert_eq!(board.shortest_path(&Location(2, 0), &Location(2, 2)), None);
+    assert_eq!(board.shortest_path(&Location(0, 0), &Location(5, 0)), None);
+    assert_eq!(
+      board.shortest_path(&Location(0, 0), &Location(0, 4)),
+      Some((0..5).map(|y| Location(0, y)).collect())
+    );
+  }
+
+  #[test]
+  fn paths_step_between_neighbours_from_start_to_end() {
+    let players = HashSet::from([Player::Player1, Player::Player2]);
+    let mut rng = ChaCha8Rng::seed_from_u64(1);
+    let layout = BoardLayout::standard(Board::STANDARD_SIZE).unwrap();
+    let board = Board::new(&mut rng, &players, &layout).unwrap();
+    let locations: Vec<Location> = board.locations().collect();
+
+    for from in &locations {
+      let reachable = board.reachable_from(from);
+      for to in &locations {
+        let path = match board.shortest_path(from, to) {
+          Some(path) => path,
+          None => {
+            assert!(!reachable.contains(to), "from {} to {}", from, to);
+            continue;
+          }
+        };
+
+        assert!(reachable.contains(to), "from {} to {}", from, to);
+        assert_eq!(path.first(), Some(from));
+        assert_eq!(path.last(), Some(to));
+        assert_eq!(path.iter().collect::<HashSet<_>>().len(), path.len());
+        for step in path.windows(2) {
+          assert!(
+            board.neighbors(&step[0]).unwrap().contains(&step[1]),
+            "from {} to {}, step {} to {}",
+            from,
+            to,
+            step[0],
+            step[1]
+          );
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn a_straight_spare_is_offered_once_per_insertion_location() {
+    let board = columns_with_a_closed_middle();
+    let insertions = board.legal_insertions();
+
+    // Turning a straight tile half way round leaves it the same, and only across the line of
+    // insertion does it keep its paths on the board
+    assert_eq!(insertions.len(), board.insertion_locations().len());
+    for (location, rotation) in insertions {
+      let placed = PlacedTile {
+        tile: board.spare,
+        rotation,
+        players: HashSet::new(),
+      };
+      assert!(
+        Board::tile_placement_ok(5, &location, &placed),
+        "{} {:?}",
+        location,
+        rotation
+      );
+    }
+  }
+}
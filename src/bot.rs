@@ -0,0 +1,259 @@
+use crate::board::{Board, Location, Player, TileMarking};
+use crate::controller::{Command, CommandRequest, Snapshot};
+use crate::errors::{LabyrinthError, LabyrinthResult};
+use crate::events::GameEvent;
+use crate::mcts::{MctsBot, SearchBudget};
+use crate::model::TurnPhase;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// A computer player, choosing what to do from what it can see of the game
+pub trait Bot {
+  /// Choose the next command to send. Only called when it is the bot's turn.
+  fn choose(&mut self, snapshot: &Snapshot) -> Command;
+}
+
+impl<B: Bot + ?Sized> Bot for Box<B> {
+  fn choose(&mut self, snapshot: &Snapshot) -> Command {
+    (**self).choose(snapshot)
+  }
+}
+
+/// Every kind of bot, so that bots can be chosen by name and created wherever they are needed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BotKind {
+  Random,
+  Greedy,
+  Mcts(SearchBudget),
+}
+
+impl BotKind {
+  /// Create a bot of this kind. Bots created with the same seed make the same choices.
+  pub fn create(&self, seed: u64) -> Box<dyn Bot> {
+    let rng = ChaCha8Rng::seed_from_u64(seed);
+
+    match self {
+      BotKind::Random => Box::new(RandomBot::new(rng)),
+      BotKind::Greedy => Box::new(GreedyBot::new(rng)),
+      BotKind::Mcts(budget) => Box::new(MctsBot::new(rng, *budget)),
+    }
+  }
+}
+
+impl fmt::Display for BotKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      BotKind::Random => write!(f, "random"),
+      BotKind::Greedy => write!(f, "greedy"),
+      BotKind::Mcts(_) => write!(f, "mcts"),
+    }
+  }
+}
+
+/// Plays uniformly random legal insertions and moves
+pub struct RandomBot<R: Rng> {
+  rng: R,
+}
+
+impl<R: Rng> RandomBot<R> {
+  pub fn new(rng: R) -> Self {
+    RandomBot { rng }
+  }
+}
+
+impl<R: Rng> Bot for RandomBot<R> {
+  fn choose(&mut self, snapshot: &Snapshot) -> Command {
+    let board = snapshot.to_board();
+    let player = snapshot.next_player;
+
+    match snapshot.turn_phase {
+      TurnPhase::InsertTile => {
+        let (location, rotation) = board
+          .legal_insertions()
+          .into_iter()
+          .choose(&mut self.rng)
+          .unwrap();
+        Command::InsertTile(location, rotation)
+      }
+      _ => {
+        let from = board.player_location(&player).unwrap();
+        let mut destinations: Vec<Location> = board.reachable_from(&from).into_iter().collect();
+        // Keep the order the same every time so games can be repeated from the bot's seed
+        destinations.sort_by_key(|location| (location.1, location.0));
+        let to = destinations.into_iter().choose(&mut self.rng).unwrap();
+        Command::MovePlayer(player, to)
+      }
+    }
+  }
+}
+
+/// Plays the insertion and move that leave it closest to the item it is looking for, or to its
+/// start once it has found everything.
+/// Distance is the length of the path to the target, or if there is no path then how far away the
+/// target is in a straight line, so the bot at least heads in the right direction.
+/// Ties are broken at random, so bots don't go round in circles forever.
+pub struct GreedyBot<R: Rng> {
+  rng: R,
+}
+
+impl<R: Rng> GreedyBot<R> {
+  pub fn new(rng: R) -> Self {
+    GreedyBot { rng }
+  }
+
+  /// Where the bot is trying to get to on this board, if the target is on the board at all
+  fn target(board: &Board, snapshot: &Snapshot) -> Option<Location> {
+    let player = snapshot.next_player;
+
+    board
+      .placed
+      .iter()
+      .find(|(_, tile)| match snapshot.looking_for {
+        Some(item) => tile.tile.marking == Some(TileMarking::Item(item)),
+        None => tile.is_start_of(player),
+      })
+      .map(|(location, _)| *location)
+  }
+
+  /// How far from the target it would be to stand at `at`, smaller is better
+  fn distance(board: &Board, at: &Location, target: Option<Location>) -> (usize, usize) {
+    let target = match target {
+      Some(target) => target,
+      None => return (0, 0),
+    };
+
+    let straight_line = at.0.abs_diff(target.0) + at.1.abs_diff(target.1);
+    match board.shortest_path(at, &target) {
+      Some(path) => (0, path.len()),
+      None => (1, straight_line),
+    }
+  }
+
+  /// The best places to move to on this board, and how far they are from the target
+  fn best_moves(board: &Board, snapshot: &Snapshot) -> (Vec<Location>, (usize, usize)) {
+    let from = board.player_location(&snapshot.next_player).unwrap();
+    let target = Self::target(board, snapshot);
+
+    let mut destinations: Vec<Location> = board.reachable_from(&from).into_iter().collect();
+    // Keep the order the same every time so games can be repeated from the bot's seed
+    destinations.sort_by_key(|location| (location.1, location.0));
+
+    best_by_key(destinations, |to| Self::distance(board, to, target))
+  }
+}
+
+impl<R: Rng> Bot for GreedyBot<R> {
+  fn choose(&mut self, snapshot: &Snapshot) -> Command {
+    let board = snapshot.to_board();
+
+    match snapshot.turn_phase {
+      TurnPhase::InsertTile => {
+        let (insertions, _) = best_by_key(board.legal_insertions(), |(location, rotation)| {
+          let mut board = board.clone();
+          board.insert_spare(*location, *rotation).unwrap();
+          Self::best_moves(&board, snapshot).1
+        });
+        let (location, rotation) = *insertions.choose(&mut self.rng).unwrap();
+        Command::InsertTile(location, rotation)
+      }
+      _ => {
+        let (destinations, _) = Self::best_moves(&board, snapshot);
+        let to = *destinations.choose(&mut self.rng).unwrap();
+        Command::MovePlayer(snapshot.next_player, to)
+      }
+    }
+  }
+}
+
+/// All the items with the smallest key, in their original order, and that key
+fn best_by_key<T, K: Ord + Copy>(items: Vec<T>, mut key: impl FnMut(&T) -> K) -> (Vec<T>, K) {
+  let keyed: Vec<(T, K)> = items
+    .into_iter()
+    .map(|item| {
+      let k = key(&item);
+      (item, k)
+    })
+    .collect();
+  let best = keyed.iter().map(|(_, k)| *k).min().unwrap();
+
+  (
+    keyed
+      .into_iter()
+      .filter(|(_, k)| *k == best)
+      .map(|(item, _)| item)
+      .collect(),
+    best,
+  )
+}
+
+/// Send a command to the controller as a player and wait for the response.
+/// Fails with `Disconnected` if the controller has stopped.
+pub fn send(
+  controller_tx: &Sender<CommandRequest>,
+  sent_by: Player,
+  command: Command,
+) -> LabyrinthResult<Snapshot> {
+  let (respond_tx, respond_rx) = channel();
+
+  controller_tx
+    .send(CommandRequest {
+      sent_by,
+      command,
+      respond: respond_tx,
+    })
+    .map_err(|_| LabyrinthError::Disconnected)?;
+
+  respond_rx
+    .recv()
+    .map_err(|_| LabyrinthError::Disconnected)?
+}
+
+/// Let a bot take its whole turn, returning the snapshot once it has finished.
+/// Returns straight away if it is not the bot's turn.
+pub fn take_turn(
+  bot: &mut dyn Bot,
+  player: Player,
+  controller_tx: &Sender<CommandRequest>,
+) -> LabyrinthResult<Snapshot> {
+  let mut snapshot = send(controller_tx, player, Command::NoOp)?;
+
+  while snapshot.next_player == player && snapshot.standings.is_none() {
+    let command = bot.choose(&snapshot);
+    snapshot = send(controller_tx, player, command)?;
+  }
+
+  Ok(snapshot)
+}
+
+/// Play as `player` until the game is over, taking the bot's turns as they come round.
+/// Meant to be run on its own thread alongside `run_controller_with_subscriptions`, with each bot on
+/// its own sender and subscribed to the game's events before it starts, so that it can wait for its
+/// turn. Fails with `Disconnected` if the events stop before the game is over.
+pub fn run_bot(
+  mut bot: impl Bot,
+  player: Player,
+  controller_tx: Sender<CommandRequest>,
+  events: Receiver<GameEvent>,
+) -> LabyrinthResult<()> {
+  loop {
+    let snapshot = take_turn(&mut bot, player, &controller_tx)?;
+
+    if snapshot.standings.is_some() {
+      return Ok(());
+    }
+
+    // Wait until it might be the bot's turn. Turns handed to the bot while it was taking its own
+    // turn are already waiting, which only means one more look at the game.
+    loop {
+      match events.recv() {
+        Ok(GameEvent::TurnChanged { player: next, .. }) if next == player => break,
+        Ok(GameEvent::GameOver { .. }) => return Ok(()),
+        Ok(_) => {}
+        Err(_) => return Err(LabyrinthError::Disconnected),
+      }
+    }
+  }
+}
@@ -0,0 +1,567 @@
+use labyrinth::bot::{Bot, BotKind};
+use labyrinth::mcts::SearchBudget;
+use labyrinth::{
+  Board, BoardLayout, DealingPolicy, GameConfig, LabyrinthError, LabyrinthResult, Player,
+};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: labyrinth <command> [options]
+
+Commands:
+  play                  Play a game in the terminal
+  replay <log>          Replay a saved game log and show the board
+  render <save>         Show the board of a saved game
+  simulate              Play a tournament between bots and report who won
+  serve                 Play a game with players connecting over TCP or WebSockets
+  layout                Print the standard board layout, to design a new one from
+
+Game options (play, simulate and serve):
+  --seat <player>=<human|random|greedy|mcts>
+                        Who plays a seat, e.g. --seat 1=human --seat 3=mcts.
+                        bot is short for greedy and hard for mcts.
+                        Players 1 and 2 play if no seats are given
+  --seed <number>       Seed for the board and cards, random if not given
+  --start <player>      Who goes first, the lowest seated player if not given
+  --board-size <number> How many tiles across the board is, an odd number of at
+                        least 5, default 7. Up to 13 when playing in the terminal
+  --layout <path>       Make the board from a layout file, .toml or .json
+  --cards-per-player <number>
+                        Deal fewer cards for a shorter game
+  --shared-pile         Deal one card each, drawing the next from a shared pile
+  --no-undo             Do not allow players to undo their moves
+  --mcts-iterations <number>
+                        How many games mcts bots play out each turn, at least 1,
+                        default 300
+  --mcts-millis <number>
+                        How long mcts bots think each turn, instead of a number
+                        of games
+
+Play and serve options:
+  --save-log <path>     Save a log of the game when it ends
+  --save <path>         Save the game when it ends
+
+Replay options:
+  --to <number>         Only replay this many commands
+
+Simulate options:
+  --games <number>      How many games to play, default 1. The bots move round
+                        one seat each game, so each plays every start position
+  --max-turns <number>  Give up on a game after this many turns, default 1000
+  --threads <number>    How many games to play at once, default one per CPU
+  --format <text|csv|json>
+                        How to write the report, default text
+
+Serve options:
+  --address <host:port> Where to listen for players, default 127.0.0.1:7777.
+                        Each human seat is given to a player connecting, in turn
+  --websocket           Serve WebSockets for web browsers instead of plain TCP.
+                        Each human seat gets a token to connect with, printed
+                        when the server starts
+
+Layout options:
+  --board-size <number> The size of board to print the layout of, default 7
+  --json                Print the layout as JSON rather than TOML";
+
+/// Who is sitting in a player's seat
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Seat {
+  /// Plays at the keyboard, or from another computer when serving a game
+  Human,
+  /// A bot making random moves
+  RandomBot,
+  /// A bot heading straight for the item it is looking for
+  GreedyBot,
+  /// A bot that searches ahead by playing out many possible games
+  MctsBot,
+}
+
+impl FromStr for Seat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "human" => Ok(Seat::Human),
+      "random" => Ok(Seat::RandomBot),
+      "greedy" | "bot" => Ok(Seat::GreedyBot),
+      "mcts" | "hard" => Ok(Seat::MctsBot),
+      _ => Err(format!(
+        "Unknown seat '{}', expected human, random, greedy or mcts",
+        s
+      )),
+    }
+  }
+}
+
+/// How to write the report of a simulation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+  Text,
+  Csv,
+  Json,
+}
+
+impl FromStr for ReportFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "text" => Ok(ReportFormat::Text),
+      "csv" => Ok(ReportFormat::Csv),
+      "json" => Ok(ReportFormat::Json),
+      _ => Err(format!(
+        "Unknown report format '{}', expected text, csv or json",
+        s
+      )),
+    }
+  }
+}
+
+/// How to set up a new game
+pub struct GameOptions {
+  pub seats: HashMap<Player, Seat>,
+  pub seed: Option<u64>,
+  pub starting_player: Option<Player>,
+  pub dealing: DealingPolicy,
+  pub allow_undo: bool,
+  pub mcts_budget: SearchBudget,
+  pub board_size: Option<usize>,
+  /// The path of a file with the layout to make the board from
+  pub layout: Option<String>,
+}
+
+impl GameOptions {
+  fn new() -> Self {
+    GameOptions {
+      seats: HashMap::new(),
+      seed: None,
+      starting_player: None,
+      dealing: DealingPolicy::default(),
+      allow_undo: true,
+      mcts_budget: SearchBudget::Iterations(300),
+      board_size: None,
+      layout: None,
+    }
+  }
+
+  /// Seat players 1 and 2 if no seats were chosen
+  fn fill_default_seats(&mut self, seat: Seat) {
+    if self.seats.is_empty() {
+      self.seats.insert(Player::Player1, seat);
+      self.seats.insert(Player::Player2, seat);
+    }
+  }
+
+  /// The kind of bot playing in a seat, or None for a human
+  pub fn bot_kind(&self, player: Player) -> Option<BotKind> {
+    match self.seats.get(&player)? {
+      Seat::Human => None,
+      Seat::RandomBot => Some(BotKind::Random),
+      Seat::GreedyBot => Some(BotKind::Greedy),
+      Seat::MctsBot => Some(BotKind::Mcts(self.mcts_budget)),
+    }
+  }
+
+  /// Create a bot for every seat not played by a human
+  pub fn bots(&self, seed: u64) -> HashMap<Player, Box<dyn Bot>> {
+    self
+      .seats
+      .keys()
+      .filter_map(|player| {
+        // Each bot gets its own seed so they don't all make the same choices
+        let bot = self
+          .bot_kind(*player)?
+          .create(seed.wrapping_add(*player as u64));
+        Some((*player, bot))
+      })
+      .collect()
+  }
+
+  /// Create the config for a new game, with the board made from the layout loaded from
+  /// `self.layout` if there is one, checking that the game can be created
+  pub fn to_config(&self, seed: u64, layout: Option<BoardLayout>) -> LabyrinthResult<GameConfig> {
+    let players = self.seats.keys().copied().collect();
+    let starting_player = match self.starting_player {
+      Some(player) => player,
+      None => *self
+        .seats
+        .keys()
+        .min_by_key(|player| **player as u8)
+        .ok_or_else(|| LabyrinthError::InvalidConfig {
+          reason: "at least one player must take part".to_string(),
+        })?,
+    };
+
+    let config = GameConfig {
+      seed: self.seed.unwrap_or(seed),
+      players,
+      starting_player,
+      allow_undo: self.allow_undo,
+      dealing: self.dealing,
+      board_size: self
+        .board_size
+        .or(layout.as_ref().map(|layout| layout.size))
+        .unwrap_or(Board::STANDARD_SIZE),
+      layout,
+    };
+    config.validate()?;
+
+    Ok(config)
+  }
+}
+
+pub enum Cli {
+  Play {
+    options: GameOptions,
+    save_log: Option<String>,
+    save: Option<String>,
+  },
+  Replay {
+    log: String,
+    to: Option<usize>,
+  },
+  Render {
+    save: String,
+  },
+  Simulate {
+    options: GameOptions,
+    games: u32,
+    max_turns: u32,
+    threads: Option<usize>,
+    format: ReportFormat,
+  },
+  Serve {
+    options: GameOptions,
+    address: String,
+    websocket: bool,
+    save_log: Option<String>,
+    save: Option<String>,
+  },
+  Layout {
+    board_size: usize,
+    json: bool,
+  },
+  Help,
+}
+
+/// Parse the command line arguments, not including the program name
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+  let mut args = args.into_iter();
+
+  let command = match args.next() {
+    Some(command) => command,
+    None => return Ok(Cli::Help),
+  };
+
+  match command.as_str() {
+    "play" => {
+      let mut options = GameOptions::new();
+      let mut save_log = None;
+      let mut save = None;
+
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--save-log" => save_log = Some(value(&mut args, &arg)?),
+          "--save" => save = Some(value(&mut args, &arg)?),
+          _ => parse_game_option(&mut options, &arg, &mut args)?,
+        }
+      }
+      options.fill_default_seats(Seat::Human);
+
+      Ok(Cli::Play {
+        options,
+        save_log,
+        save,
+      })
+    }
+    "replay" => {
+      let mut log = None;
+      let mut to = None;
+
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--to" => to = Some(parse_value(&mut args, &arg)?),
+          _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+          _ if log.is_none() => log = Some(arg),
+          _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+      }
+
+      Ok(Cli::Replay {
+        log: log.ok_or("replay needs the path of a game log")?,
+        to,
+      })
+    }
+    "render" => {
+      let save = args.next().ok_or("render needs the path of a saved game")?;
+      if let Some(arg) = args.next() {
+        return Err(format!("Unexpected argument {}", arg));
+      }
+
+      Ok(Cli::Render { save })
+    }
+    "simulate" => {
+      let mut options = GameOptions::new();
+      let mut games = 1;
+      let mut max_turns = 1000;
+      let mut threads = None;
+      let mut format = ReportFormat::Text;
+
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--games" => games = parse_value(&mut args, &arg)?,
+          "--max-turns" => max_turns = parse_value(&mut args, &arg)?,
+          "--threads" => threads = Some(parse_value(&mut args, &arg)?),
+          "--format" => format = value(&mut args, &arg)?.parse()?,
+          _ => parse_game_option(&mut options, &arg, &mut args)?,
+        }
+      }
+      options.fill_default_seats(Seat::GreedyBot);
+
+      if options.seats.values().any(|seat| *seat == Seat::Human) {
+        return Err("Only bots can play in a simulation".to_string());
+      }
+
+      Ok(Cli::Simulate {
+        options,
+        games,
+        max_turns,
+        threads,
+        format,
+      })
+    }
+    "serve" => {
+      let mut options = GameOptions::new();
+      let mut address = "127.0.0.1:7777".to_string();
+      let mut websocket = false;
+      let mut save_log = None;
+      let mut save = None;
+
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--address" => address = value(&mut args, &arg)?,
+          "--websocket" => websocket = true,
+          "--save-log" => save_log = Some(value(&mut args, &arg)?),
+          "--save" => save = Some(value(&mut args, &arg)?),
+          _ => parse_game_option(&mut options, &arg, &mut args)?,
+        }
+      }
+      options.fill_default_seats(Seat::Human);
+
+      Ok(Cli::Serve {
+        options,
+        address,
+        websocket,
+        save_log,
+        save,
+      })
+    }
+    "layout" => {
+      let mut board_size = Board::STANDARD_SIZE;
+      let mut json = false;
+
+      while let Some(arg) = args.next() {
+        match arg.as_str() {
+          "--board-size" => board_size = parse_value(&mut args, &arg)?,
+          "--json" => json = true,
+          _ => return Err(format!("Unknown option {}", arg)),
+        }
+      }
+
+      Ok(Cli::Layout { board_size, json })
+    }
+    "help" | "--help" | "-h" => Ok(Cli::Help),
+    _ => Err(format!("Unknown command {}", command)),
+  }
+}
+
+fn parse_game_option(
+  options: &mut GameOptions,
+  arg: &str,
+  args: &mut impl Iterator<Item = String>,
+) -> Result<(), String> {
+  match arg {
+    "--seat" => {
+      let seat = value(args, arg)?;
+      let (player, seat) = seat.split_once('=').ok_or_else(|| {
+        format!(
+          "Expected --seat <player>=<human|random|greedy|mcts>, not {}",
+          seat
+        )
+      })?;
+      let player = parse_player(player)?;
+
+      if options.seats.insert(player, seat.parse()?).is_some() {
+        return Err(format!("{:?} has been given more than one seat", player));
+      }
+    }
+    "--seed" => options.seed = Some(parse_value(args, arg)?),
+    "--start" => options.starting_player = Some(parse_player(&value(args, arg)?)?),
+    "--board-size" => options.board_size = Some(parse_value(args, arg)?),
+    "--layout" => options.layout = Some(value(args, arg)?),
+    "--cards-per-player" | "--shared-pile" if options.dealing != DealingPolicy::default() => {
+      return Err("Choose only one of --cards-per-player and --shared-pile".to_string())
+    }
+    "--cards-per-player" => options.dealing = DealingPolicy::FixedHandSize(parse_value(args, arg)?),
+    "--shared-pile" => options.dealing = DealingPolicy::SharedPile,
+    "--no-undo" => options.allow_undo = false,
+    "--mcts-iterations" => {
+      let iterations: NonZeroU32 = parse_value(args, arg)?;
+      options.mcts_budget = SearchBudget::Iterations(iterations.get())
+    }
+    "--mcts-millis" => {
+      options.mcts_budget = SearchBudget::Time(Duration::from_millis(parse_value(args, arg)?))
+    }
+    _ => return Err(format!("Unknown option {}", arg)),
+  }
+
+  Ok(())
+}
+
+/// Players can be given as a number, e.g. 3, or by name, e.g. player3
+fn parse_player(s: &str) -> Result<Player, String> {
+  let number = s.to_lowercase();
+  let number = number.strip_prefix("player").unwrap_or(&number);
+
+  match number {
+    "1" => Ok(Player::Player1),
+    "2" => Ok(Player::Player2),
+    "3" => Ok(Player::Player3),
+    "4" => Ok(Player::Player4),
+    _ => Err(format!("Unknown player '{}', expected 1 to 4", s)),
+  }
+}
+
+/// The value following an option
+fn value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+  args
+    .next()
+    .ok_or_else(|| format!("{} needs a value", option))
+}
+
+fn parse_value<T: FromStr>(
+  args: &mut impl Iterator<Item = String>,
+  option: &str,
+) -> Result<T, String> {
+  let value = value(args, option)?;
+  value
+    .parse()
+    .map_err(|_| format!("Invalid value '{}' for {}", value, option))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_args(args: &str) -> Result<Cli, String> {
+    parse(args.split_whitespace().map(str::to_string))
+  }
+
+  fn game_options(args: &str) -> GameOptions {
+    match parse_args(args) {
+      Ok(
+        Cli::Play { options, .. } | Cli::Simulate { options, .. } | Cli::Serve { options, .. },
+      ) => options,
+      Ok(_) => panic!("{} is not a game", args),
+      Err(error) => panic!("{}: {}", args, error),
+    }
+  }
+
+  #[test]
+  fn each_subcommand_is_parsed_with_its_options() {
+    assert!(matches!(parse_args(""), Ok(Cli::Help)));
+    assert!(matches!(parse_args("--help"), Ok(Cli::Help)));
+    assert!(matches!(
+      parse_args("play --save-log game.json"),
+      Ok(Cli::Play { save_log: Some(path), save: None, .. }) if path == "game.json"
+    ));
+    assert!(matches!(
+      parse_args("replay game.json --to 12"),
+      Ok(Cli::Replay { log, to: Some(12) }) if log == "game.json"
+    ));
+    assert!(matches!(
+      parse_args("render save.json"),
+      Ok(Cli::Render { save }) if save == "save.json"
+    ));
+    assert!(matches!(
+      parse_args("simulate --games 10 --max-turns 50 --threads 2 --format csv"),
+      Ok(Cli::Simulate {
+        games: 10,
+        max_turns: 50,
+        threads: Some(2),
+        format: ReportFormat::Csv,
+        ..
+      })
+    ));
+    assert!(matches!(
+      parse_args("serve --address 0.0.0.0:80 --websocket"),
+      Ok(Cli::Serve { address, websocket: true, .. }) if address == "0.0.0.0:80"
+    ));
+    assert!(matches!(
+      parse_args("layout --board-size 9 --json"),
+      Ok(Cli::Layout {
+        board_size: 9,
+        json: true
+      })
+    ));
+
+    assert!(parse_args("jump").is_err());
+    assert!(parse_args("render").is_err());
+    assert!(parse_args("replay a.json b.json").is_err());
+    assert!(parse_args("play --games 3").is_err());
+  }
+
+  #[test]
+  fn seats_are_given_to_players_by_number_or_name() {
+    let options = game_options("play --seat 1=human --seat player3=Random --seat 4=bot");
+    assert_eq!(options.seats.len(), 3);
+    assert_eq!(options.seats[&Player::Player1], Seat::Human);
+    assert_eq!(options.seats[&Player::Player3], Seat::RandomBot);
+    assert_eq!(options.seats[&Player::Player4], Seat::GreedyBot);
+    assert_eq!(
+      game_options("serve --seat 2=hard").seats[&Player::Player2],
+      Seat::MctsBot
+    );
+
+    // Players 1 and 2 play if nobody is seated, as humans or as greedy bots in a simulation
+    let seats = game_options("simulate").seats;
+    assert_eq!(seats.len(), 2);
+    assert!(seats.values().all(|seat| *seat == Seat::GreedyBot));
+
+    assert!(parse_args("play --seat 1=human --seat 1=mcts").is_err());
+    assert!(parse_args("play --seat 5=human").is_err());
+    assert!(parse_args("play --seat 1").is_err());
+    assert!(parse_args("play --seat 1=expert").is_err());
+    assert!(parse_args("simulate --seat 1=human --seat 2=mcts").is_err());
+  }
+
+  #[test]
+  fn mcts_bots_must_play_out_at_least_one_game() {
+    assert!(parse_args("simulate --mcts-iterations 0").is_err());
+    assert_eq!(
+      game_options("simulate --mcts-iterations 1").mcts_budget,
+      SearchBudget::Iterations(1)
+    );
+    assert_eq!(
+      game_options("simulate --mcts-millis 20").mcts_budget,
+      SearchBudget::Time(Duration::from_millis(20))
+    );
+  }
+
+  #[test]
+  fn boards_must_be_an_odd_size_of_at_least_5() {
+    assert!(parse_args("play --board-size seven").is_err());
+    assert!(parse_args("play --board-size -7").is_err());
+    assert!(parse_args("layout --board-size").is_err());
+
+    for size in [4, 6, 3] {
+      let options = game_options(&format!("play --board-size {}", size));
+      assert!(options.to_config(1, None).is_err(), "size {}", size);
+    }
+    let options = game_options("play --board-size 9");
+    assert_eq!(options.to_config(1, None).unwrap().board_size, 9);
+  }
+}
@@ -1,57 +1,127 @@
-use crate::board::{Item, Location, PlacedTile, Player, Rotation, Tile};
-use crate::errors::{GenericError, GenericResult, TurnError, WrongPlayer};
-use crate::model::{Cards, Model, TurnPhase};
+use crate::board::{Board, Item, Location, PlacedTile, Player, Rotation, Tile};
+use crate::errors::{LabyrinthError, LabyrinthResult};
+use crate::events::Subscriptions;
+use crate::model::{Cards, Change, DealingPolicy, Model, Standing, TurnPhase};
+use crate::replay::GameLog;
 use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::sync::mpsc::{Receiver, Sender};
 
+/// What everyone can see of a player's cards: the ones they have found and how many are left,
+/// but never which items they are still looking for
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 pub struct CardsSnapshot {
-  found: HashSet<Item>,
-  num_hidden_cards: u32,
+  pub found: HashSet<Item>,
+  pub num_remaining: u32,
 }
 
 impl From<&Cards> for CardsSnapshot {
   fn from(cards: &Cards) -> CardsSnapshot {
     CardsSnapshot {
       found: cards.found_cards.clone(),
-      num_hidden_cards: cards.hidden_cards.len() as u32,
+      num_remaining: cards.remaining() as u32,
     }
   }
 }
 
+/// Who a snapshot is made for, which decides what secret information it can show
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Viewer {
+  /// A player sees the item they are looking for, but not what anyone else is looking for
+  Player(Player),
+  /// A spectator sees nothing secret
+  Spectator,
+}
+
+/// What a player can see of the game
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 pub struct Snapshot {
-  board: HashMap<Location, PlacedTile>,
-  spare_tile: Tile,
-  next_player: Player,
-  looking_for: Option<Item>,
-  players: HashMap<Player, CardsSnapshot>,
+  /// How many tiles there are along each side of the board
+  pub board_size: usize,
+  #[cfg_attr(feature = "serde", serde(with = "crate::board::placed_serde"))]
+  pub board: HashMap<Location, PlacedTile>,
+  pub spare_tile: Tile,
+  pub next_player: Player,
+  pub turn_phase: TurnPhase,
+  pub forbidden_insertion: Option<Location>,
+  /// The item the viewer is looking for, if they are a player
+  pub looking_for: Option<Item>,
+  pub players: HashMap<Player, CardsSnapshot>,
+  /// How many cards are left in the shared draw pile
+  pub draw_pile: u32,
+  /// How the cards were dealt, which decides where found cards are drawn from
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub dealing: DealingPolicy,
+  /// The final standings, once the game is over
+  pub standings: Option<Vec<Standing>>,
 }
 
 impl Snapshot {
-  /// Create a new snapshot of the game state, showing only info visible to `player`
-  fn for_player(model: &Model, player: Player) -> Snapshot {
+  /// Create a new snapshot of the game state, showing only info visible to `viewer`
+  pub fn for_viewer(model: &Model, viewer: Viewer) -> Snapshot {
+    let looking_for = match viewer {
+      Viewer::Player(player) => model
+        .players
+        .get(&player)
+        .and_then(|cards| cards.current_card),
+      Viewer::Spectator => None,
+    };
+
     Snapshot {
+      board_size: model.board.size(),
       board: model.board.placed.clone(),
       spare_tile: model.board.spare,
       next_player: model.current_player,
-      looking_for: model.current_player_cards().current_card,
+      turn_phase: model.turn_phase,
+      forbidden_insertion: model.board.forbidden_insertion(),
+      looking_for,
       players: model
         .players
         .iter()
         .map(|(player, cards)| (*player, CardsSnapshot::from(cards)))
         .collect(),
+      draw_pile: model.draw_pile.len() as u32,
+      dealing: model.config.dealing,
+      standings: if model.is_game_over() {
+        Some(model.standings())
+      } else {
+        None
+      },
     }
   }
+
+  /// Create a new snapshot of the game state, showing only info visible to `player`
+  pub fn for_player(model: &Model, player: Player) -> Snapshot {
+    Snapshot::for_viewer(model, Viewer::Player(player))
+  }
+
+  /// Recreate the board shown in the snapshot, e.g. to draw it or work out where players can go
+  pub fn to_board(&self) -> Board {
+    Board::from_placed(
+      self.board_size,
+      self.board.clone(),
+      self.spare_tile,
+      self.forbidden_insertion,
+    )
+  }
 }
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 pub enum Command {
   NoOp,
   MovePlayer(Player, Location),
   InsertTile(Location, Rotation),
+  /// Take back the last insertion or move, which must have been made by the sender, until the game
+  /// is over
+  Undo,
+  /// Make the last undone insertion or move again, until the game is over
+  Redo,
 }
 
-type SnapshotSender = Sender<GenericResult<Snapshot>>;
+type SnapshotSender = Sender<LabyrinthResult<Snapshot>>;
 
 #[derive(Debug)]
 pub struct CommandRequest {
@@ -60,82 +130,176 @@ pub struct CommandRequest {
   pub respond: SnapshotSender,
 }
 
-pub fn run_controller(mut model: Model, command_rx: Receiver<CommandRequest>) {
-  for request in command_rx {
-    println!("{:?} sent command {:?}", request.sent_by, request.command);
+/// Commands that have changed the game, so they can be undone and redone
+#[derive(Default)]
+pub struct History {
+  applied: Vec<(Player, Command, Change)>,
+  undone: Vec<(Player, Command)>,
+}
 
-    if request.sent_by != model.current_player {
-      respond_error(&request, WrongPlayer::new("It is not your turn").into());
-      continue;
-    }
+impl History {
+  fn undo(&mut self, model: &mut Model, sent_by: Player) -> LabyrinthResult<()> {
+    match self.applied.last() {
+      None => Err(LabyrinthError::NothingToUndo),
+      Some((player, _, _)) if *player != sent_by => Err(LabyrinthError::NotYourMove {
+        expected: *player,
+        actual: sent_by,
+      }),
+      Some(_) => {
+        let (player, command, change) = self.applied.pop().unwrap();
+        model.undo(&change);
+        self.undone.push((player, command));
 
-    match request.command {
-      Command::NoOp => respond_snapshot(&request, &model),
-      Command::MovePlayer(_, _) if model.turn_phase != TurnPhase::Move => respond_error(
-        &request,
-        TurnError::new("It is not time to move, you must first insert the tile").into(),
-      ),
-      Command::MovePlayer(player, _) if player != model.current_player => respond_error(
-        &request,
-        WrongPlayer::new("You cannot move another player").into(),
-      ),
-      Command::MovePlayer(player, location) => {
-        do_then_respond(&mut model, &request, &mut |model| {
-          move_player(player, location, model)
-        })
+        Ok(())
       }
-      Command::InsertTile(_, _) if model.turn_phase != TurnPhase::InsertTile => respond_error(
-        &request,
-        TurnError::new("It is not time to insert the tile, you must move").into(),
-      ),
-      Command::InsertTile(location, rotation) => {
-        do_then_respond(&mut model, &request, &mut |model| {
-          Ok(model.board.insert_spare(location, rotation)?)
-        })
+    }
+  }
+
+  fn redo(&mut self, model: &mut Model, sent_by: Player) -> LabyrinthResult<()> {
+    match self.undone.last() {
+      None => Err(LabyrinthError::NothingToRedo),
+      Some((player, _)) if *player != sent_by => Err(LabyrinthError::NotYourMove {
+        expected: *player,
+        actual: sent_by,
+      }),
+      Some((player, command)) => {
+        // Only forget the undone command once it has been made again, so it can still be redone
+        // if it fails
+        let (player, command) = (*player, command.clone());
+        let change = apply_change(model, player, &command)?.ok_or(LabyrinthError::NothingToRedo)?;
+        self.undone.pop();
+        self.applied.push((player, command, change));
+
+        Ok(())
       }
     }
   }
 }
 
-fn respond_snapshot(request: &CommandRequest, model: &Model) {
-  request
-    .respond
-    .send(Ok(Snapshot::for_player(&model, request.sent_by)))
-    .unwrap()
+/// Run the game, applying commands as they are received until every command sender has hung up.
+/// Returns a log of every command that changed the game, which can be replayed.
+pub fn run_controller(model: Model, command_rx: Receiver<CommandRequest>) -> GameLog {
+  run_controller_with_subscriptions(model, command_rx, &Subscriptions::default())
 }
 
-fn respond_error(request: &CommandRequest, error: GenericError) {
-  request.respond.send(Err(error)).unwrap();
+/// Run the game like `run_controller`, also sending the subscribers events for every change to the
+/// game as it is made, before the sender of the command is answered
+pub fn run_controller_with_subscriptions(
+  mut model: Model,
+  command_rx: Receiver<CommandRequest>,
+  subscriptions: &Subscriptions,
+) -> GameLog {
+  let mut log = GameLog::new(model.config.clone());
+  let mut history = History::default();
+
+  for request in command_rx {
+    match apply_command(&mut model, &mut history, request.sent_by, &request.command) {
+      Ok(()) => {
+        match request.command {
+          Command::NoOp => {}
+          Command::Undo => subscriptions.publish_undo(&model, request.sent_by),
+          // Every other command that succeeds has just made the latest change
+          _ => {
+            let (player, _, change) = history.applied.last().unwrap();
+            subscriptions.publish_change(&model, *player, change);
+          }
+        }
+        if !matches!(request.command, Command::NoOp) {
+          log.record(request.sent_by, request.command.clone());
+        }
+        respond_snapshot(&request, &model)
+      }
+      Err(error) => respond_error(&request, error),
+    }
+  }
+
+  log
 }
 
-/// Call the function and respond to the request.
-/// If the function returns an `Ok(_)` then send a snapshot of the model from the players view.
-/// If the function returns an `Err(error)` then forward this `error` on to the command requester.
-fn do_then_respond<F: FnMut(&mut Model) -> GenericResult<()>>(
+/// Check that a command sent by a player is allowed, and if so apply it to the model
+pub fn apply_command(
   model: &mut Model,
-  request: &CommandRequest,
-  f: &mut F,
-) {
-  match f(model) {
-    Ok(_) => respond_snapshot(request, model),
-    Err(error) => respond_error(request, error),
+  history: &mut History,
+  sent_by: Player,
+  command: &Command,
+) -> LabyrinthResult<()> {
+  // Everyone has already been told who won, so the winning move cannot be taken back
+  if let (Command::Undo | Command::Redo, TurnPhase::GameOver { winner }) =
+    (command, model.turn_phase)
+  {
+    return Err(LabyrinthError::GameOver { winner });
+  }
+
+  match command {
+    Command::Undo | Command::Redo if !model.config.allow_undo => {
+      Err(LabyrinthError::UndoNotAllowed)
+    }
+    Command::Undo => history.undo(model, sent_by),
+    Command::Redo => history.redo(model, sent_by),
+    _ => {
+      if let Some(change) = apply_change(model, sent_by, command)? {
+        history.applied.push((sent_by, command.clone(), change));
+        history.undone.clear();
+      }
+
+      Ok(())
+    }
   }
 }
 
-/// Move a player across the board and end their turn
-fn move_player(player: Player, location: Location, model: &mut Model) -> GenericResult<()> {
-  model.board.move_player(&player, &location)?;
+/// Apply a command that changes the game, returning how to undo the change.
+/// Returns Ok(None) if the command does not change the game.
+fn apply_change(
+  model: &mut Model,
+  sent_by: Player,
+  command: &Command,
+) -> LabyrinthResult<Option<Change>> {
+  // Anyone can look at the game, whether or not it is their turn
+  if let Command::NoOp = command {
+    return Ok(None);
+  }
 
-  let player_cards = model.current_player_cards();
+  if let TurnPhase::GameOver { winner } = model.turn_phase {
+    return Err(LabyrinthError::GameOver { winner });
+  }
 
-  if player_cards.current_card.is_some()
-    && player_cards.current_card == model.board.item_at(&location).unwrap()
-  {
-    // Player has found the item they're looking for, draw the next item card
-    model.current_player_cards_mut().draw_next();
+  if sent_by != model.current_player {
+    return Err(LabyrinthError::NotYourTurn {
+      expected: model.current_player,
+      actual: sent_by,
+    });
   }
 
-  model.end_turn();
+  match *command {
+    Command::NoOp | Command::Undo | Command::Redo => Ok(None),
+    Command::MovePlayer(_, _) if model.turn_phase != TurnPhase::Move => {
+      Err(LabyrinthError::WrongTurnPhase {
+        required: TurnPhase::Move,
+        actual: model.turn_phase,
+      })
+    }
+    Command::MovePlayer(player, _) if player != model.current_player => {
+      Err(LabyrinthError::CannotMoveOtherPlayer { player, sent_by })
+    }
+    Command::MovePlayer(player, location) => Ok(Some(model.move_player(player, location)?)),
+    Command::InsertTile(_, _) if model.turn_phase != TurnPhase::InsertTile => {
+      Err(LabyrinthError::WrongTurnPhase {
+        required: TurnPhase::InsertTile,
+        actual: model.turn_phase,
+      })
+    }
+    Command::InsertTile(location, rotation) => Ok(Some(model.insert_tile(location, rotation)?)),
+  }
+}
+
+// A sender that has stopped waiting for its response must not stop the game for everyone else, so
+// responses that cannot be sent are dropped
+fn respond_snapshot(request: &CommandRequest, model: &Model) {
+  let _ = request
+    .respond
+    .send(Ok(Snapshot::for_player(model, request.sent_by)));
+}
 
-  Ok(())
+fn respond_error(request: &CommandRequest, error: LabyrinthError) {
+  let _ = request.respond.send(Err(error));
 }
@@ -1,113 +1,251 @@
-// error1.rs
-use crate::Location;
+use crate::board::{Location, Player, Rotation};
+use crate::model::TurnPhase;
 use std::convert::From;
 use std::error::Error;
 use std::fmt;
 
-pub type GenericError = Box<dyn Error + Send + Sync>;
-pub type GenericResult<T> = Result<T, GenericError>;
+pub type LabyrinthResult<T> = Result<T, LabyrinthError>;
 
-#[derive(Debug)]
-pub struct LocationError {
-  details: String,
+/// Everything that can go wrong when playing a game.
+/// Each error has a stable code so clients can tell errors apart without parsing the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabyrinthError {
+  /// There is no tile at the location
+  InvalidLocation {
+    location: Location,
+  },
+  /// The spare tile cannot be inserted at the location
+  InvalidInsertion {
+    location: Location,
+  },
+  /// Inserting the spare tile with this rotation would leave a path leading off the board
+  InvalidRotation {
+    location: Location,
+    rotation: Rotation,
+  },
+  /// Inserting the spare tile here would push back the tile that was just pushed out
+  ReversedInsertion {
+    location: Location,
+  },
+  /// There is no path for the player to follow to the destination
+  NoPath {
+    from: Location,
+    to: Location,
+  },
+  /// The player's pawn could not be found on the board
+  PlayerNotOnBoard {
+    player: Player,
+  },
+  /// The player is not taking part in the game
+  PlayerNotPlaying {
+    player: Player,
+  },
+  /// It is another player's turn
+  NotYourTurn {
+    expected: Player,
+    actual: Player,
+  },
+  /// Players may only move their own pawn
+  CannotMoveOtherPlayer {
+    player: Player,
+    sent_by: Player,
+  },
+  /// The command is not allowed in this part of the turn
+  WrongTurnPhase {
+    required: TurnPhase,
+    actual: TurnPhase,
+  },
+  /// No more changes can be made once the game has been won
+  GameOver {
+    winner: Player,
+  },
+  /// Undo and redo have been turned off for this game
+  UndoNotAllowed,
+  NothingToUndo,
+  NothingToRedo,
+  /// Every player playing from another computer has left, so the game cannot go on
+  GameAbandoned,
+  /// The game is no longer being played, so commands and events can no longer be sent
+  Disconnected,
+  /// Players may only undo or redo their own insertions and moves
+  NotYourMove {
+    expected: Player,
+    actual: Player,
+  },
+  /// The board is missing tiles or items
+  InvalidBoard {
+    reason: String,
+  },
+  /// A saved game could not be read
+  InvalidSave {
+    reason: String,
+  },
+  /// A game cannot be created with these settings
+  InvalidConfig {
+    reason: String,
+  },
+  /// A board layout could not be read, or a board cannot be made from it
+  InvalidLayout {
+    reason: String,
+  },
+  /// A message sent to the server could not be read
+  InvalidMessage {
+    reason: String,
+  },
+  /// The game has got into a state that the rules should never allow
+  BrokenInvariant {
+    reason: String,
+  },
+  /// A simulated game went wrong, and can be played again from its seed to find out why
+  GameFailed {
+    seed: u64,
+    reason: String,
+  },
 }
 
-impl From<&Location> for LocationError {
-  fn from(location: &Location) -> Self {
-    LocationError {
-      details: format!("Invalid location {}", location),
+impl LabyrinthError {
+  /// A short, stable name for the kind of error
+  pub fn code(&self) -> &'static str {
+    match self {
+      LabyrinthError::InvalidLocation { .. } => "invalid_location",
+      LabyrinthError::InvalidInsertion { .. } => "invalid_insertion",
+      LabyrinthError::InvalidRotation { .. } => "invalid_rotation",
+      LabyrinthError::ReversedInsertion { .. } => "reversed_insertion",
+      LabyrinthError::NoPath { .. } => "no_path",
+      LabyrinthError::PlayerNotOnBoard { .. } => "player_not_on_board",
+      LabyrinthError::PlayerNotPlaying { .. } => "player_not_playing",
+      LabyrinthError::NotYourTurn { .. } => "not_your_turn",
+      LabyrinthError::CannotMoveOtherPlayer { .. } => "cannot_move_other_player",
+      LabyrinthError::WrongTurnPhase { .. } => "wrong_turn_phase",
+      LabyrinthError::GameOver { .. } => "game_over",
+      LabyrinthError::GameAbandoned => "game_abandoned",
+      LabyrinthError::Disconnected => "disconnected",
+      LabyrinthError::UndoNotAllowed => "undo_not_allowed",
+      LabyrinthError::NothingToUndo => "nothing_to_undo",
+      LabyrinthError::NothingToRedo => "nothing_to_redo",
+      LabyrinthError::NotYourMove { .. } => "not_your_move",
+      LabyrinthError::InvalidBoard { .. } => "invalid_board",
+      LabyrinthError::InvalidSave { .. } => "invalid_save",
+      LabyrinthError::InvalidConfig { .. } => "invalid_config",
+      LabyrinthError::InvalidLayout { .. } => "invalid_layout",
+      LabyrinthError::InvalidMessage { .. } => "invalid_message",
+      LabyrinthError::BrokenInvariant { .. } => "broken_invariant",
+      LabyrinthError::GameFailed { .. } => "game_failed",
     }
   }
-}
 
-impl LocationError {
-  pub fn new(message: &str) -> Self {
-    LocationError {
-      details: message.to_string(),
+  /// A stable number for the kind of error.
+  /// Board errors are 1xx, turn errors 2xx, undo errors 3xx, loading errors 4xx and errors that
+  /// mean there is a bug 5xx.
+  pub fn number(&self) -> u16 {
+    match self {
+      LabyrinthError::InvalidLocation { .. } => 100,
+      LabyrinthError::InvalidInsertion { .. } => 101,
+      LabyrinthError::InvalidRotation { .. } => 102,
+      LabyrinthError::ReversedInsertion { .. } => 103,
+      LabyrinthError::NoPath { .. } => 104,
+      LabyrinthError::PlayerNotOnBoard { .. } => 105,
+      LabyrinthError::PlayerNotPlaying { .. } => 200,
+      LabyrinthError::NotYourTurn { .. } => 201,
+      LabyrinthError::CannotMoveOtherPlayer { .. } => 202,
+      LabyrinthError::WrongTurnPhase { .. } => 203,
+      LabyrinthError::GameOver { .. } => 204,
+      LabyrinthError::GameAbandoned => 205,
+      LabyrinthError::Disconnected => 206,
+      LabyrinthError::UndoNotAllowed => 300,
+      LabyrinthError::NothingToUndo => 301,
+      LabyrinthError::NothingToRedo => 302,
+      LabyrinthError::NotYourMove { .. } => 303,
+      LabyrinthError::InvalidBoard { .. } => 400,
+      LabyrinthError::InvalidSave { .. } => 401,
+      LabyrinthError::InvalidConfig { .. } => 402,
+      LabyrinthError::InvalidLayout { .. } => 403,
+      LabyrinthError::InvalidMessage { .. } => 404,
+      LabyrinthError::BrokenInvariant { .. } => 500,
+      LabyrinthError::GameFailed { .. } => 501,
     }
   }
 }
 
-impl fmt::Display for LocationError {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.details)
-  }
-}
-
-impl Error for LocationError {
-  fn description(&self) -> &str {
-    &self.details
-  }
-}
-
-#[derive(Debug)]
-pub struct MoveError {
-  details: String,
-}
-
-impl MoveError {
-  pub fn new(message: &str) -> MoveError {
-    MoveError {
-      details: message.to_string(),
+impl From<&Location> for LabyrinthError {
+  fn from(location: &Location) -> Self {
+    LabyrinthError::InvalidLocation {
+      location: *location,
     }
   }
 }
-impl fmt::Display for MoveError {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.details)
-  }
-}
-
-impl Error for MoveError {
-  fn description(&self) -> &str {
-    &self.details
-  }
-}
 
-#[derive(Debug)]
-pub struct WrongPlayer {
-  details: String,
-}
-
-impl WrongPlayer {
-  pub fn new(message: &str) -> WrongPlayer {
-    WrongPlayer {
-      details: message.to_string(),
-    }
-  }
-}
-impl fmt::Display for WrongPlayer {
+impl fmt::Display for LabyrinthError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.details)
-  }
-}
-
-impl Error for WrongPlayer {
-  fn description(&self) -> &str {
-    &self.details
-  }
-}
-
-#[derive(Debug)]
-pub struct TurnError {
-  details: String,
-}
-
-impl TurnError {
-  pub fn new(message: &str) -> TurnError {
-    TurnError {
-      details: message.to_string(),
+    match self {
+      LabyrinthError::InvalidLocation { location } => write!(f, "Invalid location {}", location),
+      LabyrinthError::InvalidInsertion { location } => {
+        write!(f, "Cannot insert a tile at location {}", location)
+      }
+      LabyrinthError::InvalidRotation { location, rotation } => write!(
+        f,
+        "Tile cannot be inserted at {} with rotation {:?}",
+        location, rotation
+      ),
+      LabyrinthError::ReversedInsertion { location } => write!(
+        f,
+        "Cannot insert a tile at {}, it would undo the previous insertion",
+        location
+      ),
+      LabyrinthError::NoPath { from, to } => write!(f, "No path from {} to {}", from, to),
+      LabyrinthError::PlayerNotOnBoard { player } => {
+        write!(f, "Player {:?} not found on board", player)
+      }
+      LabyrinthError::PlayerNotPlaying { player } => {
+        write!(f, "Player {:?} is not playing", player)
+      }
+      LabyrinthError::NotYourTurn { expected, actual } => write!(
+        f,
+        "It is not your turn, it is {:?}'s turn not {:?}'s",
+        expected, actual
+      ),
+      LabyrinthError::CannotMoveOtherPlayer { player, sent_by } => {
+        write!(f, "{:?} cannot move another player {:?}", sent_by, player)
+      }
+      LabyrinthError::WrongTurnPhase {
+        required: TurnPhase::Move,
+        ..
+      } => write!(f, "It is not time to move, you must first insert the tile"),
+      LabyrinthError::WrongTurnPhase {
+        required: TurnPhase::InsertTile,
+        ..
+      } => write!(f, "It is not time to insert the tile, you must move"),
+      LabyrinthError::WrongTurnPhase { required, actual } => write!(
+        f,
+        "Expected turn phase {:?} but it is {:?}",
+        required, actual
+      ),
+      LabyrinthError::GameOver { winner } => {
+        write!(f, "The game is over, {:?} has won", winner)
+      }
+      LabyrinthError::GameAbandoned => {
+        write!(f, "Every remote player has left, so the game is over")
+      }
+      LabyrinthError::Disconnected => write!(f, "The game is no longer being played"),
+      LabyrinthError::UndoNotAllowed => write!(f, "Undo is not allowed in this game"),
+      LabyrinthError::NothingToUndo => write!(f, "There is nothing to undo"),
+      LabyrinthError::NothingToRedo => write!(f, "There is nothing to redo"),
+      LabyrinthError::NotYourMove { expected, actual } => write!(
+        f,
+        "Only {:?} can undo or redo their own move, not {:?}",
+        expected, actual
+      ),
+      LabyrinthError::InvalidBoard { reason } => write!(f, "Invalid board: {}", reason),
+      LabyrinthError::InvalidSave { reason } => write!(f, "Invalid saved game: {}", reason),
+      LabyrinthError::InvalidConfig { reason } => write!(f, "Invalid game settings: {}", reason),
+      LabyrinthError::InvalidLayout { reason } => write!(f, "Invalid board layout: {}", reason),
+      LabyrinthError::InvalidMessage { reason } => write!(f, "Invalid message: {}", reason),
+      LabyrinthError::BrokenInvariant { reason } => write!(f, "Broken invariant: {}", reason),
+      LabyrinthError::GameFailed { seed, reason } => {
+        write!(f, "Game with seed {} failed: {}", seed, reason)
+      }
     }
   }
 }
-impl fmt::Display for TurnError {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.details)
-  }
-}
 
-impl Error for TurnError {
-  fn description(&self) -> &str {
-    &self.details
-  }
-}
+impl Error for LabyrinthError {}
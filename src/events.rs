@@ -0,0 +1,184 @@
+//! Events telling players and spectators how the game changes, sent as each change is made.
+//!
+//! Players and spectators subscribe to a game's events with [`Subscriptions::subscribe`], and are
+//! only told what they are allowed to see. The subscriptions are given to
+//! [`run_controller_with_subscriptions`](crate::controller::run_controller_with_subscriptions),
+//! and can be subscribed to before or while the game is played.
+
+use crate::board::{Item, Location, Player, Rotation, Tile};
+use crate::controller::{Snapshot, Viewer};
+use crate::model::{Change, Model, Standing, TurnPhase};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Something that happened in the game
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+  /// The spare tile was inserted, pushing the tile at the far end of its row or column off the
+  /// board to become the new spare tile
+  TileInserted {
+    player: Player,
+    at: Location,
+    rotation: Rotation,
+    pushed_out: Tile,
+  },
+  /// A player moved along the paths. `path` is a shortest route from where they were, first, to
+  /// where they are now, last, which is not always the way the player had in mind.
+  PlayerMoved { player: Player, path: Vec<Location> },
+  /// A player reached the item they were looking for
+  CardFound {
+    player: Player,
+    item: Item,
+    /// The item the player looks for next, only sent to that player
+    next: Option<Item>,
+  },
+  /// It is now `player`'s turn to do what `phase` says
+  TurnChanged { player: Player, phase: TurnPhase },
+  /// A player has won
+  GameOver {
+    winner: Player,
+    standings: Vec<Standing>,
+  },
+  /// A player took back their last insertion or move, which can put anything back as it was
+  Undone {
+    player: Player,
+    /// The whole game as it is now, as the subscriber sees it
+    game: Box<Snapshot>,
+  },
+}
+
+/// Where to send each subscriber's events, with who they are watching as. Each sender is shared so
+/// that it can be picked out again after events have been sent to a copy of the list.
+type Sinks = Mutex<Vec<(Viewer, Arc<Sender<GameEvent>>)>>;
+
+/// Everyone listening for a game's events.
+/// Clones share the same subscribers, so one can be kept to subscribe with while the game runs.
+#[derive(Debug, Clone, Default)]
+pub struct Subscriptions {
+  sinks: Arc<Sinks>,
+}
+
+impl Subscriptions {
+  /// Receive the events of every change made from now on, as `viewer` sees them.
+  /// Events stop being sent once the receiver is dropped.
+  pub fn subscribe(&self, viewer: Viewer) -> Receiver<GameEvent> {
+    let (sink, events) = channel();
+    self.sinks.lock().unwrap().push((viewer, Arc::new(sink)));
+    events
+  }
+
+  /// Stop sending events to everyone subscribed so far, e.g. once nobody is left to play the game
+  #[cfg(feature = "serde")]
+  pub(crate) fn close(&self) {
+    self.sinks.lock().unwrap().clear();
+  }
+
+  /// Tell every subscriber about a change that has just been made to the model
+  pub(crate) fn publish_change(&self, model: &Model, player: Player, change: &Change) {
+    self.publish(|viewer| events_for_change(model, player, change, viewer));
+  }
+
+  /// Tell every subscriber that a change has just been undone
+  pub(crate) fn publish_undo(&self, model: &Model, player: Player) {
+    self.publish(|viewer| {
+      vec![
+        GameEvent::Undone {
+          player,
+          game: Box::new(Snapshot::for_viewer(model, viewer)),
+        },
+        turn_event(model),
+      ]
+    });
+  }
+
+  /// Send each subscriber their events. The events are made and sent without holding the lock, so
+  /// that subscribing is never held up by a snapshot being made.
+  fn publish(&self, events_for: impl Fn(Viewer) -> Vec<GameEvent>) {
+    let sinks = self.sinks.lock().unwrap().clone();
+    let stopped: Vec<_> = sinks
+      .into_iter()
+      .filter(|(viewer, sink)| {
+        !events_for(*viewer)
+          .into_iter()
+          .all(|event| sink.send(event).is_ok())
+      })
+      .map(|(_, sink)| sink)
+      .collect();
+
+    // Forget subscribers that have stopped listening
+    if !stopped.is_empty() {
+      self
+        .sinks
+        .lock()
+        .unwrap()
+        .retain(|(_, sink)| !stopped.iter().any(|gone| Arc::ptr_eq(gone, sink)));
+    }
+  }
+}
+
+/// The events for a change made by `player`, as `viewer` sees them. The model must be as it is
+/// just after the change.
+pub fn events_for_change(
+  model: &Model,
+  player: Player,
+  change: &Change,
+  viewer: Viewer,
+) -> Vec<GameEvent> {
+  let mut events = Vec::new();
+
+  match change {
+    Change::TileInserted(insertion) => events.push(GameEvent::TileInserted {
+      player,
+      at: insertion.insert_at,
+      rotation: model.board.placed[&insertion.insert_at].rotation,
+      pushed_out: model.board.spare,
+    }),
+    Change::PlayerMoved {
+      player,
+      from,
+      found,
+    } => {
+      // Moving never changes the paths, so there is always a route to where the player is now. If
+      // there ever is not, the move is shown as a jump straight there.
+      let to = model.board.player_location(player).unwrap_or(*from);
+      let path = model
+        .board
+        .shortest_path(from, &to)
+        .unwrap_or_else(|| vec![*from, to]);
+      events.push(GameEvent::PlayerMoved {
+        player: *player,
+        path,
+      });
+
+      if let Some(item) = found {
+        let next = match viewer {
+          Viewer::Player(viewer) if viewer == *player => model.players[player].current_card,
+          _ => None,
+        };
+        events.push(GameEvent::CardFound {
+          player: *player,
+          item: *item,
+          next,
+        });
+      }
+    }
+  }
+
+  events.push(turn_event(model));
+  events
+}
+
+/// Whose turn it is now, or who won if the game is over
+fn turn_event(model: &Model) -> GameEvent {
+  match model.turn_phase {
+    TurnPhase::GameOver { winner } => GameEvent::GameOver {
+      winner,
+      standings: model.standings(),
+    },
+    phase => GameEvent::TurnChanged {
+      player: model.current_player,
+      phase,
+    },
+  }
+}
@@ -0,0 +1,295 @@
+//! Play a game over WebSockets, e.g. from a web browser, with each remote player connecting with
+//! their own secret token.
+//!
+//! Clients connect to the gateway's address with their token in the query string, e.g.
+//! `ws://127.0.0.1:7777/?token=...`, and can connect in any order. The messages are the same as
+//! the `server` module's, with one JSON message in each WebSocket text message. The game starts
+//! once every remote player has connected.
+
+use crate::board::Player;
+use crate::bot::BotKind;
+use crate::controller::CommandRequest;
+use crate::events::Subscriptions;
+use crate::server::{play, Seat, ServerMessage};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+/// How long a client may take to send its handshake before it is turned away, so that a client
+/// that connects and says nothing does not keep its thread waiting forever
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Who plays a game served over WebSockets
+#[derive(Debug, Clone)]
+pub struct Gateway {
+  /// The token each remote player connects with, which decides which player they play
+  pub tokens: HashMap<Player, String>,
+  /// The bots playing the other players, which start once every client has connected
+  pub bots: Vec<(Player, BotKind)>,
+  /// Seed for the bots' choices
+  pub seed: u64,
+}
+
+impl Gateway {
+  /// A gateway giving each remote player a new random token
+  pub fn new(remote_players: &[Player], bots: Vec<(Player, BotKind)>, seed: u64) -> Self {
+    let mut rng = rand::thread_rng();
+    let tokens = remote_players
+      .iter()
+      .map(|player| (*player, format!("{:032x}", rng.gen::<u128>())))
+      .collect();
+
+    Gateway { tokens, bots, seed }
+  }
+}
+
+/// Wait for every remote player to connect with their token, then play the game by passing
+/// everyone's commands on to the controller, which must be sending its events to `subscriptions`.
+/// Each client's handshake happens on its own thread, so a slow client cannot hold up anyone else
+/// connecting. Connections with a token that is not a remote player's, or whose player is already
+/// connected, are turned away. Returns once every client has disconnected and every bot has
+/// stopped.
+pub fn serve_websockets(
+  gateway: &Gateway,
+  listener: &TcpListener,
+  controller_tx: Sender<CommandRequest>,
+  subscriptions: &Subscriptions,
+) -> io::Result<()> {
+  let tokens = Arc::new(gateway.tokens.clone());
+  let claimed = Arc::new(Mutex::new(HashSet::new()));
+  let wake_at = local_address(listener)?;
+  let seated = AtomicBool::new(false);
+  let (seated_tx, seated_rx) = channel();
+
+  let clients = thread::scope(|scope| {
+    let accepting = scope.spawn(|| -> io::Result<()> {
+      // Only the listener and the handshakes it starts can seat anyone, so once they have all
+      // stopped the wait for seats ends
+      let seated_tx = seated_tx;
+      loop {
+        let (stream, _) = listener.accept()?;
+        if seated.load(Ordering::SeqCst) {
+          return Ok(());
+        }
+
+        let tokens = tokens.clone();
+        let claimed = claimed.clone();
+        let seated_tx = seated_tx.clone();
+        thread::spawn(move || {
+          if let Some(client) = handshake(&tokens, &claimed, stream) {
+            let _ = seated_tx.send(client);
+          }
+        });
+      }
+    });
+
+    let mut clients = HashMap::new();
+    while clients.len() < gateway.tokens.len() {
+      match seated_rx.recv() {
+        Ok((player, socket)) => {
+          clients.insert(player, socket);
+        }
+        Err(_) => break,
+      }
+    }
+
+    // Wake the listener up so that it sees everyone has a seat
+    seated.store(true, Ordering::SeqCst);
+    let _ = TcpStream::connect(wake_at);
+    accepting.join().unwrap().map(|_| clients)
+  })?;
+
+  // Everyone has a seat, so the game can start
+  play(
+    clients.into_iter().collect(),
+    &gateway.bots,
+    gateway.seed,
+    controller_tx,
+    subscriptions,
+    talk_over_websocket,
+  );
+
+  Ok(())
+}
+
+/// Where the listener can be connected to from this machine
+fn local_address(listener: &TcpListener) -> io::Result<SocketAddr> {
+  let mut address = listener.local_addr()?;
+  if address.ip().is_unspecified() {
+    match address {
+      SocketAddr::V4(_) => address.set_ip(Ipv4Addr::LOCALHOST.into()),
+      SocketAddr::V6(_) => address.set_ip(Ipv6Addr::LOCALHOST.into()),
+    }
+  }
+
+  Ok(address)
+}
+
+/// Take a client through the WebSocket handshake, seating it as the player whose token it sent.
+/// Returns None if the client was turned away or left, giving back the seat if it had one.
+fn handshake(
+  tokens: &HashMap<Player, String>,
+  claimed: &Mutex<HashSet<Player>>,
+  stream: TcpStream,
+) -> Option<(Player, WebSocket<Connection>)> {
+  let connection = Connection::new(stream).ok()?;
+
+  let mut seated = None;
+  // The handshake's callback must return tungstenite's error response as it is
+  #[allow(clippy::result_large_err)]
+  let check_token = |request: &Request, response: Response| match seat_for(tokens, claimed, request)
+  {
+    Ok(player) => {
+      seated = Some(player);
+      Ok(response)
+    }
+    Err(status) => Err(error_response(status)),
+  };
+  let socket = tungstenite::accept_hdr(connection, check_token).ok();
+  let player = seated?;
+
+  let socket = socket.and_then(|mut socket| {
+    socket.get_ref().reader.set_read_timeout(None).ok()?;
+    send_message(&mut socket, &ServerMessage::Seated { player }).ok()?;
+    Some(socket)
+  });
+  match socket {
+    Some(socket) => Some((player, socket)),
+    None => {
+      // A client that could not be seated must not stop its player from connecting again
+      claimed.lock().unwrap().remove(&player);
+      None
+    }
+  }
+}
+
+/// Find the player whose token a client connected with, and claim their seat
+fn seat_for(
+  tokens: &HashMap<Player, String>,
+  claimed: &Mutex<HashSet<Player>>,
+  request: &Request,
+) -> Result<Player, StatusCode> {
+  // Browsers cannot add headers to WebSocket requests, so the token is sent in the query string
+  let token = request
+    .uri()
+    .query()
+    .unwrap_or("")
+    .split('&')
+    .find_map(|parameter| parameter.strip_prefix("token="))
+    .ok_or(StatusCode::UNAUTHORIZED)?;
+
+  let player = tokens
+    .iter()
+    .find(|(_, expected)| same_token(expected, token))
+    .map(|(player, _)| *player)
+    .ok_or(StatusCode::UNAUTHORIZED)?;
+
+  if claimed.lock().unwrap().insert(player) {
+    Ok(player)
+  } else {
+    Err(StatusCode::CONFLICT)
+  }
+}
+
+/// Whether a token is the one expected. Every byte is compared however early they differ, so the
+/// time taken cannot tell a client how much of a guess was right.
+fn same_token(expected: &str, token: &str) -> bool {
+  expected.len() == token.len()
+    && expected
+      .bytes()
+      .zip(token.bytes())
+      .fold(0, |differ, (a, b)| differ | (a ^ b))
+      == 0
+}
+
+fn error_response(status: StatusCode) -> ErrorResponse {
+  let mut response = ErrorResponse::new(status.canonical_reason().map(str::to_string));
+  *response.status_mut() = status;
+  response
+}
+
+/// Read a client's commands until it disconnects, sending it its messages from another thread
+fn talk_over_websocket(
+  seat: &Seat,
+  inbox: Receiver<ServerMessage>,
+  mut socket: WebSocket<Connection>,
+) {
+  let mut writer = match socket.get_ref().try_clone() {
+    Ok(connection) => WebSocket::from_raw_socket(connection, Role::Server, None),
+    Err(_) => return,
+  };
+  // Stops once the client has left and every message to it has been sent, or it cannot be sent to
+  thread::spawn(move || {
+    for message in inbox {
+      if send_message(&mut writer, &message).is_err() {
+        break;
+      }
+    }
+  });
+
+  loop {
+    match socket.read() {
+      Ok(Message::Text(text)) => seat.handle(text.as_str()),
+      // Replies to pings and closes are sent by the read itself
+      Ok(_) => {}
+      Err(_) => return,
+    }
+  }
+}
+
+/// A client's connection, which one thread can read from while another writes to it.
+/// Each write is sent whole before the next begins, so frames written from different threads are
+/// never mixed up.
+struct Connection {
+  reader: TcpStream,
+  writer: Arc<Mutex<TcpStream>>,
+}
+
+impl Connection {
+  fn new(stream: TcpStream) -> io::Result<Self> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    Ok(Connection {
+      reader: stream.try_clone()?,
+      writer: Arc::new(Mutex::new(stream)),
+    })
+  }
+
+  fn try_clone(&self) -> io::Result<Self> {
+    Ok(Connection {
+      reader: self.reader.try_clone()?,
+      writer: self.writer.clone(),
+    })
+  }
+}
+
+impl Read for Connection {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    self.reader.read(buf)
+  }
+}
+
+impl Write for Connection {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.writer.lock().unwrap().write_all(buf)?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.writer.lock().unwrap().flush()
+  }
+}
+
+fn send_message(socket: &mut WebSocket<Connection>, message: &ServerMessage) -> io::Result<()> {
+  let text = serde_json::to_string(message)?;
+  socket.send(Message::text(text)).map_err(io::Error::other)
+}
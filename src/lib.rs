@@ -0,0 +1,36 @@
+//! A game engine for the board game Labyrinth.
+//!
+//! The [`Model`] holds the state of a game and the [`Board`] the maze of tiles. Games are played by
+//! running [`run_controller`] on its own thread and sending it [`CommandRequest`]s, which are
+//! answered with a [`Snapshot`] of what the sending player can see, while [`Subscriptions`] tell
+//! players and spectators about every change as it happens. With the `serde` feature, the
+//! `server` module lets clients play over TCP, and with the `websocket` feature the `gateway`
+//! module lets them play over WebSockets.
+
+pub mod board;
+pub mod bot;
+pub mod controller;
+pub mod emoji;
+pub mod errors;
+pub mod events;
+#[cfg(feature = "websocket")]
+pub mod gateway;
+pub mod mcts;
+pub mod model;
+pub mod packed;
+pub mod replay;
+#[cfg(feature = "serde")]
+pub mod server;
+pub mod tournament;
+
+pub use board::{
+  Board, BoardLayout, Item, Location, PlacedTile, Player, Rotation, Tile, TileMarking,
+};
+pub use controller::{
+  run_controller, run_controller_with_subscriptions, CardsSnapshot, Command, CommandRequest,
+  Snapshot, Viewer,
+};
+pub use errors::{LabyrinthError, LabyrinthResult};
+pub use events::{GameEvent, Subscriptions};
+pub use model::{DealingPolicy, GameConfig, Model, Standing, Turn, TurnPhase};
+pub use replay::{replay, replay_to, GameLog, LogEvent};
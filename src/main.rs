@@ -1,45 +1,422 @@
-mod board;
-mod controller;
-mod emoji;
-mod errors;
-mod model;
+mod cli;
+mod terminal;
 
-use controller::{run_controller, Command, CommandRequest};
-use model::Model;
-use std::collections::HashSet;
+use cli::{Cli, GameOptions, ReportFormat, USAGE};
+#[cfg(feature = "serde")]
+use labyrinth::bot::BotKind;
+#[cfg(feature = "websocket")]
+use labyrinth::gateway::{self, Gateway};
+#[cfg(feature = "serde")]
+use labyrinth::server::{self, Server};
+use labyrinth::tournament::{run_tournament, Tournament, TournamentReport};
+use labyrinth::{run_controller, BoardLayout, GameLog, Model, Snapshot, Viewer};
+#[cfg(feature = "serde")]
+use labyrinth::{run_controller_with_subscriptions, CommandRequest, Player, Subscriptions};
+use rand::Rng;
+use std::error::Error;
+#[cfg(feature = "serde")]
+use std::net::TcpListener;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::process;
+#[cfg(feature = "serde")]
+use std::sync::mpsc::Sender;
 
 use std::sync::mpsc::channel;
 use std::thread;
 
-use board::{Board, Location, Player, Rotation};
-
 fn main() {
-    println!("Hello, world!");
+    let cli = match cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match cli {
+        Cli::Play {
+            options,
+            save_log,
+            save,
+        } => play(&options, save_log, save),
+        Cli::Replay { log, to } => replay(&log, to),
+        Cli::Render { save } => render(&save),
+        Cli::Simulate {
+            options,
+            games,
+            max_turns,
+            threads,
+            format,
+        } => simulate(&options, games, max_turns, threads, format),
+        Cli::Serve {
+            options,
+            address,
+            websocket,
+            save_log,
+            save,
+        } => serve(&options, &address, websocket, save_log, save),
+        Cli::Layout { board_size, json } => layout(board_size, json),
+        Cli::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    };
+
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn play(
+    options: &GameOptions,
+    save_log: Option<String>,
+    save: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    // Find out that the game cannot be saved before playing it, rather than after
+    #[cfg(not(feature = "serde"))]
+    if save_log.is_some() || save.is_some() {
+        return Err(NEEDS_SERDE.into());
+    }
+
+    let config = options.to_config(rand::thread_rng().gen(), load_layout(options)?)?;
+    if config.board_size > terminal::MAX_BOARD_SIZE {
+        return Err(format!(
+            "Boards larger than {} tiles across cannot be played in the terminal",
+            terminal::MAX_BOARD_SIZE
+        )
+        .into());
+    }
+    println!("Game seed: {}", config.seed);
+
+    let model = Model::new(&config)?;
+
+    let (controller_tx, controller_rx) = channel();
+
+    let controller_handle = thread::spawn(move || run_controller(model, controller_rx));
+
+    let mut bots = options.bots(config.seed);
+    let played = terminal::play(&controller_tx, &config.players, &mut bots);
+
+    drop(controller_tx);
+    let log = controller_handle.join().unwrap();
+    played?;
+
+    save_game(&log, save_log, save)
+}
+
+fn replay(path: &str, to: Option<usize>) -> Result<(), Box<dyn Error>> {
+    let log = load_log(path)?;
+
+    for event in log.events.iter().take(to.unwrap_or(log.events.len())) {
+        println!(
+            "{} {} {:?}: {:?}",
+            event.number, event.sent_by, event.sent_by, event.command
+        );
+    }
+
+    let model = labyrinth::replay_to(&log, to.unwrap_or(log.events.len()))?;
+    print_spectator_view(&model);
+
+    Ok(())
+}
+
+fn render(path: &str) -> Result<(), Box<dyn Error>> {
+    let model = load_game(path)?;
+    print_spectator_view(&model);
+
+    Ok(())
+}
+
+fn simulate(
+    options: &GameOptions,
+    games: u32,
+    max_turns: u32,
+    threads: Option<usize>,
+    format: ReportFormat,
+) -> Result<(), Box<dyn Error>> {
+    #[cfg(not(feature = "serde"))]
+    if format == ReportFormat::Json {
+        return Err(NO_JSON_REPORTS.into());
+    }
+
+    let config = options.to_config(rand::thread_rng().gen(), load_layout(options)?)?;
+    eprintln!("First game seed: {}", config.seed);
 
-    let mut rng = rand::thread_rng();
-    let players: HashSet<Player> = [Player::Player1, Player::Player2, Player::Player4]
+    let mut players: Vec<_> = config.players.iter().copied().collect();
+    players.sort_by_key(|player| *player as u8);
+    let seats = players
         .into_iter()
+        .map(|player| options.bot_kind(player).unwrap())
         .collect();
 
-    let current_player = Player::Player1;
+    let threads = threads.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1)
+    });
+
+    let report = run_tournament(&Tournament {
+        seats,
+        config,
+        games,
+        max_turns,
+        threads,
+    })?;
+
+    match format {
+        ReportFormat::Text => print!("{}", report),
+        ReportFormat::Csv => print!("{}", report.to_csv()),
+        ReportFormat::Json => println!("{}", report_json(&report)?),
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn serve(
+    options: &GameOptions,
+    address: &str,
+    websocket: bool,
+    save_log: Option<String>,
+    save: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    #[cfg(not(feature = "websocket"))]
+    if websocket {
+        return Err(NEEDS_WEBSOCKET.into());
+    }
 
-    let model = Model::new(&mut rng, &players, current_player).unwrap();
+    let config = options.to_config(rand::thread_rng().gen(), load_layout(options)?)?;
+    println!("Game seed: {}", config.seed);
 
+    let mut players: Vec<_> = config.players.iter().copied().collect();
+    players.sort_by_key(|player| *player as u8);
+    let (bots, remote_players): (Vec<_>, Vec<_>) = players
+        .into_iter()
+        .partition(|player| options.bot_kind(*player).is_some());
+    let bots = bots
+        .into_iter()
+        .map(|player| (player, options.bot_kind(player).unwrap()))
+        .collect();
+
+    let listener = TcpListener::bind(address)?;
+    println!(
+        "Listening on {}, waiting for {} players",
+        listener.local_addr()?,
+        remote_players.len()
+    );
+
+    let model = Model::new(&config)?;
     let (controller_tx, controller_rx) = channel();
+    let subscriptions = Subscriptions::default();
+    let controller_subscriptions = subscriptions.clone();
+    let controller_handle = thread::spawn(move || {
+        run_controller_with_subscriptions(model, controller_rx, &controller_subscriptions)
+    });
 
-    let controller_handle = thread::spawn(move || run_controller(model, controller_rx));
+    if websocket {
+        serve_websockets(
+            &listener,
+            &remote_players,
+            bots,
+            config.seed,
+            controller_tx,
+            &subscriptions,
+        )?;
+    } else {
+        let server = Server {
+            remote_players,
+            bots,
+            seed: config.seed,
+        };
+        server::serve(&server, &listener, controller_tx, &subscriptions)?;
+    }
+    let log = controller_handle.join().unwrap();
+
+    save_game(&log, save_log, save)
+}
+
+#[cfg(not(feature = "serde"))]
+fn serve(
+    _options: &GameOptions,
+    _address: &str,
+    _websocket: bool,
+    _save_log: Option<String>,
+    _save: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    Err("Serving games needs the serde feature".into())
+}
 
-    let (respond_tx, respond_rx) = channel();
+/// Give each remote player the address to connect to with their token, then play the game
+#[cfg(feature = "websocket")]
+fn serve_websockets(
+    listener: &TcpListener,
+    remote_players: &[Player],
+    bots: Vec<(Player, BotKind)>,
+    seed: u64,
+    controller_tx: Sender<CommandRequest>,
+    subscriptions: &Subscriptions,
+) -> Result<(), Box<dyn Error>> {
+    let gateway = Gateway::new(remote_players, bots, seed);
+    for player in remote_players {
+        println!(
+            "{:?}: ws://{}/?token={}",
+            player,
+            listener.local_addr()?,
+            gateway.tokens[player]
+        );
+    }
 
-    let request = CommandRequest {
-        sent_by: current_player,
-        command: Command::InsertTile(Location(3, 0), Rotation::Clockwise90),
-        respond: respond_tx,
+    gateway::serve_websockets(&gateway, listener, controller_tx, subscriptions)?;
+
+    Ok(())
+}
+
+#[cfg(all(feature = "serde", not(feature = "websocket")))]
+const NEEDS_WEBSOCKET: &str = "Serving games over WebSockets needs the websocket feature";
+
+#[cfg(all(feature = "serde", not(feature = "websocket")))]
+fn serve_websockets(
+    _listener: &TcpListener,
+    _remote_players: &[Player],
+    _bots: Vec<(Player, BotKind)>,
+    _seed: u64,
+    _controller_tx: Sender<CommandRequest>,
+    _subscriptions: &Subscriptions,
+) -> Result<(), Box<dyn Error>> {
+    Err(NEEDS_WEBSOCKET.into())
+}
+
+fn layout(board_size: usize, json: bool) -> Result<(), Box<dyn Error>> {
+    let layout = BoardLayout::standard(board_size)?;
+    if json {
+        println!("{}", save_layout_json(&layout)?);
+    } else {
+        print!("{}", save_layout_toml(&layout)?);
+    }
+
+    Ok(())
+}
+
+fn print_spectator_view(model: &Model) {
+    let snapshot = Snapshot::for_viewer(model, Viewer::Spectator);
+    println!("{}", terminal::render(&snapshot));
+}
+
+#[cfg(feature = "serde")]
+fn save_game(
+    log: &GameLog,
+    save_log: Option<String>,
+    save: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = save_log {
+        std::fs::write(path, log.save_json()?)?;
+    }
+    if let Some(path) = save {
+        std::fs::write(path, labyrinth::replay(log)?.save_json()?)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn load_log(path: &str) -> Result<GameLog, Box<dyn Error>> {
+    Ok(GameLog::load_json(&std::fs::read_to_string(path)?)?)
+}
+
+#[cfg(feature = "serde")]
+fn load_game(path: &str) -> Result<Model, Box<dyn Error>> {
+    Ok(Model::load_json(&std::fs::read_to_string(path)?)?)
+}
+
+/// Load the layout file given in the options, if there is one, as TOML or JSON depending on its
+/// extension
+#[cfg(feature = "serde")]
+fn load_layout(options: &GameOptions) -> Result<Option<BoardLayout>, Box<dyn Error>> {
+    let path = match &options.layout {
+        Some(path) => path,
+        None => return Ok(None),
     };
 
-    controller_tx.send(request).unwrap();
+    let contents = std::fs::read_to_string(path)?;
+    let layout = match Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("toml") => BoardLayout::load_toml(&contents)?,
+        Some("json") => BoardLayout::load_json(&contents)?,
+        _ => return Err(format!("Layout files must be .toml or .json, not {}", path).into()),
+    };
+
+    Ok(Some(layout))
+}
+
+#[cfg(feature = "serde")]
+fn save_layout_toml(layout: &BoardLayout) -> Result<String, Box<dyn Error>> {
+    Ok(layout.save_toml()?)
+}
+
+#[cfg(feature = "serde")]
+fn save_layout_json(layout: &BoardLayout) -> Result<String, Box<dyn Error>> {
+    Ok(layout.save_json()?)
+}
+
+#[cfg(feature = "serde")]
+fn report_json(report: &TournamentReport) -> Result<String, Box<dyn Error>> {
+    Ok(report.to_json()?)
+}
+
+#[cfg(not(feature = "serde"))]
+const NEEDS_SERDE: &str = "Saving and loading games needs the serde feature";
+
+#[cfg(not(feature = "serde"))]
+fn save_game(
+    _log: &GameLog,
+    save_log: Option<String>,
+    save: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    match (save_log, save) {
+        (None, None) => Ok(()),
+        _ => Err(NEEDS_SERDE.into()),
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_log(_path: &str) -> Result<GameLog, Box<dyn Error>> {
+    Err(NEEDS_SERDE.into())
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_game(_path: &str) -> Result<Model, Box<dyn Error>> {
+    Err(NEEDS_SERDE.into())
+}
+
+#[cfg(not(feature = "serde"))]
+const NEEDS_SERDE_FOR_LAYOUTS: &str = "Loading and saving board layouts needs the serde feature";
+
+#[cfg(not(feature = "serde"))]
+fn load_layout(options: &GameOptions) -> Result<Option<BoardLayout>, Box<dyn Error>> {
+    match options.layout {
+        None => Ok(None),
+        Some(_) => Err(NEEDS_SERDE_FOR_LAYOUTS.into()),
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn save_layout_toml(_layout: &BoardLayout) -> Result<String, Box<dyn Error>> {
+    Err(NEEDS_SERDE_FOR_LAYOUTS.into())
+}
+
+#[cfg(not(feature = "serde"))]
+fn save_layout_json(_layout: &BoardLayout) -> Result<String, Box<dyn Error>> {
+    Err(NEEDS_SERDE_FOR_LAYOUTS.into())
+}
 
-    let response = respond_rx.recv().unwrap().unwrap();
+#[cfg(not(feature = "serde"))]
+const NO_JSON_REPORTS: &str = "JSON reports need the serde feature";
 
-    controller_handle.join().unwrap();
+#[cfg(not(feature = "serde"))]
+fn report_json(_report: &TournamentReport) -> Result<String, Box<dyn Error>> {
+    Err(NO_JSON_REPORTS.into())
 }
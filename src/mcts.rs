@@ -0,0 +1,377 @@
+use crate::board::{Item, Location, Player, TileMarking};
+use crate::bot::{Bot, GreedyBot};
+use crate::controller::{Command, Snapshot};
+use crate::model::{Cards, Change, GameConfig, Model, Turn, TurnPhase};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How many turns are played quickly after leaving the tree before the game is scored
+const ROLLOUT_TURNS: usize = 8;
+/// How many places to move to are searched after each insertion, the ones closest to the target
+const CANDIDATE_MOVES: usize = 3;
+/// How much to favour exploring turns that have been tried less often
+const EXPLORATION: f64 = 0.7;
+
+/// How long the bot may think about each turn
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SearchBudget {
+  /// Play out this many games, which always gives the same choice from the same seed
+  Iterations(u32),
+  /// Play out as many games as possible in this much time
+  Time(Duration),
+}
+
+/// A position in the search tree, reached by playing every turn on the way down from the root
+struct Node {
+  /// The turn that led here and who played it, None at the root
+  turn: Option<(Player, Turn)>,
+  children: Vec<usize>,
+  /// Turns not yet tried from here, None until the node is first reached
+  untried: Option<Vec<Turn>>,
+  visits: u32,
+  /// The total score of every game played through here, for the player who played the turn
+  reward: f64,
+}
+
+impl Node {
+  fn new(turn: Option<(Player, Turn)>) -> Self {
+    Node {
+      turn,
+      children: Vec::new(),
+      untried: None,
+      visits: 0,
+      reward: 0.0,
+    }
+  }
+
+  fn upper_confidence_bound(&self, parent_visits: u32) -> f64 {
+    self.reward / self.visits as f64
+      + EXPLORATION * ((parent_visits as f64).ln() / self.visits as f64).sqrt()
+  }
+}
+
+/// Plays by Monte Carlo Tree Search over whole turns, inserting the tile and moving together.
+///
+/// The bot cannot see what the other players are looking for, so before each game it plays out it
+/// deals everyone a random hand that fits what it can see: the cards each player has found, how many
+/// they have left, and the item it is looking for itself.
+pub struct MctsBot<R: Rng> {
+  rng: R,
+  budget: SearchBudget,
+  /// Where to move after the insertion the bot has just made
+  planned_move: Option<Location>,
+}
+
+impl<R: Rng> MctsBot<R> {
+  pub fn new(rng: R, budget: SearchBudget) -> Self {
+    MctsBot {
+      rng,
+      budget,
+      planned_move: None,
+    }
+  }
+
+  /// Find the best whole turn for the player whose turn it is
+  fn search(&mut self, snapshot: &Snapshot) -> Turn {
+    let mut model = determinization_base(snapshot);
+    let mut nodes = vec![Node::new(None)];
+    let started = Instant::now();
+    let mut iterations = 0;
+
+    loop {
+      // Always play out at least one game, so that there is a turn to choose
+      let done = iterations > 0
+        && match self.budget {
+          SearchBudget::Iterations(max) => iterations >= max,
+          SearchBudget::Time(duration) => started.elapsed() >= duration,
+        };
+      if done {
+        break;
+      }
+
+      deal(&mut model, snapshot, &mut self.rng);
+      self.iterate(&mut model, &mut nodes);
+      iterations += 1;
+    }
+
+    let best = nodes[0]
+      .children
+      .iter()
+      .max_by_key(|child| nodes[**child].visits)
+      .expect("there is always a legal turn");
+    nodes[*best].turn.unwrap().1
+  }
+
+  /// Play out one game from the root, then put the model back how it was
+  fn iterate(&mut self, model: &mut Model, nodes: &mut Vec<Node>) {
+    let mut path = vec![0];
+    let mut changes: Vec<Change> = Vec::new();
+
+    // Go down the tree, choosing the most promising turns, until reaching a turn not tried before
+    let mut node = 0;
+    while !model.is_game_over() {
+      let untried = nodes[node]
+        .untried
+        .get_or_insert_with(|| candidate_turns(model));
+
+      if !untried.is_empty() {
+        let turn = untried.swap_remove(self.rng.gen_range(0..untried.len()));
+        let player = model.current_player;
+
+        if let Ok(turn_changes) = model.play_turn(&turn) {
+          changes.extend(turn_changes);
+          nodes.push(Node::new(Some((player, turn))));
+          let child = nodes.len() - 1;
+          nodes[node].children.push(child);
+          path.push(child);
+        }
+        break;
+      }
+
+      let parent_visits = nodes[node].visits;
+      let child = match nodes[node].children.iter().max_by(|a, b| {
+        let a = nodes[**a].upper_confidence_bound(parent_visits);
+        let b = nodes[**b].upper_confidence_bound(parent_visits);
+        a.total_cmp(&b)
+      }) {
+        Some(child) => *child,
+        None => break,
+      };
+
+      let (_, turn) = nodes[child].turn.unwrap();
+      match model.play_turn(&turn) {
+        Ok(turn_changes) => changes.extend(turn_changes),
+        Err(_) => break,
+      }
+      path.push(child);
+      node = child;
+    }
+
+    // Finish off with a few quick turns and see how everyone is doing
+    for _ in 0..ROLLOUT_TURNS {
+      if model.is_game_over() {
+        break;
+      }
+      changes.extend(rollout_turn(model, &mut self.rng));
+    }
+    let scores = score(model);
+
+    for node in path {
+      let node = &mut nodes[node];
+      node.visits += 1;
+      if let Some((player, _)) = node.turn {
+        node.reward += scores[&player];
+      }
+    }
+
+    for change in changes.iter().rev() {
+      model.undo(change);
+    }
+  }
+}
+
+impl<R: Rng> Bot for MctsBot<R> {
+  fn choose(&mut self, snapshot: &Snapshot) -> Command {
+    let player = snapshot.next_player;
+
+    match snapshot.turn_phase {
+      TurnPhase::InsertTile => {
+        let turn = self.search(snapshot);
+        self.planned_move = Some(turn.move_to);
+        Command::InsertTile(turn.insert_at, turn.rotation)
+      }
+      _ => {
+        let board = snapshot.to_board();
+        let from = board.player_location(&player).unwrap();
+
+        // The plan only still holds if nothing has been undone since making it
+        match self.planned_move.take() {
+          Some(to) if board.reachable_from(&from).contains(&to) => Command::MovePlayer(player, to),
+          _ => GreedyBot::new(&mut self.rng).choose(snapshot),
+        }
+      }
+    }
+  }
+}
+
+/// A model of the game in the snapshot, with the cards still to be dealt by `deal`
+fn determinization_base(snapshot: &Snapshot) -> Model {
+  let players = snapshot.players.keys().copied().collect();
+
+  Model {
+    config: GameConfig {
+      seed: 0,
+      board_size: snapshot.board_size,
+      layout: None,
+      players,
+      starting_player: snapshot.next_player,
+      allow_undo: true,
+      dealing: snapshot.dealing,
+    },
+    board: snapshot.to_board(),
+    players: HashMap::new(),
+    current_player: snapshot.next_player,
+    turn_phase: snapshot.turn_phase,
+    draw_pile: Vec::new(),
+  }
+}
+
+/// Deal every player a random hand that fits what the snapshot shows of their cards
+fn deal<R: Rng>(model: &mut Model, snapshot: &Snapshot, rng: &mut R) {
+  let mut players: Vec<Player> = snapshot.players.keys().copied().collect();
+  players.sort_by_key(|player| *player as u8);
+
+  // Any item not yet found could be in anyone's hand, apart from the one the bot is looking for
+  let mut unknown: Vec<Item> = model
+    .board
+    .items()
+    .into_iter()
+    .filter(|item| {
+      Some(*item) != snapshot.looking_for
+        && !snapshot
+          .players
+          .values()
+          .any(|cards| cards.found.contains(item))
+    })
+    .collect();
+  unknown.sort_by_key(|item| *item as u8);
+  unknown.shuffle(rng);
+
+  model.players = players
+    .iter()
+    .map(|player| {
+      let seen = &snapshot.players[player];
+      let mut remaining = seen.num_remaining as usize;
+
+      let current_card = if *player == snapshot.next_player {
+        snapshot.looking_for
+      } else if remaining > 0 {
+        unknown.pop()
+      } else {
+        None
+      };
+      remaining = remaining.saturating_sub(1);
+
+      let cards = Cards {
+        current_card,
+        hidden_cards: unknown.split_off(unknown.len().saturating_sub(remaining)),
+        found_cards: seen.found.clone(),
+      };
+      (*player, cards)
+    })
+    .collect();
+
+  model.draw_pile = unknown.split_off(unknown.len().saturating_sub(snapshot.draw_pile as usize));
+}
+
+/// The turns worth searching for the current player: every legal insertion, each followed by a
+/// move to one of the few places closest to what they are looking for.
+/// Searching every possible move would spread the search too thinly to find anything good.
+fn candidate_turns(model: &mut Model) -> Vec<Turn> {
+  let player = model.current_player;
+  let mut turns = Vec::new();
+
+  for (insert_at, rotation) in model.board.legal_insertions() {
+    let inserted = model.insert_tile(insert_at, rotation).unwrap();
+
+    turns.extend(
+      closest_moves(model, player)
+        .into_iter()
+        .take(CANDIDATE_MOVES)
+        .map(|move_to| Turn {
+          insert_at,
+          rotation,
+          move_to,
+        }),
+    );
+
+    model.undo(&inserted);
+  }
+
+  turns
+}
+
+/// Everywhere the player can move to, closest to their target first
+fn closest_moves(model: &Model, player: Player) -> Vec<Location> {
+  let from = model.board.player_location(&player).unwrap();
+  let target = target(model, player);
+
+  let mut destinations: Vec<Location> = model.board.reachable_from(&from).into_iter().collect();
+  destinations.sort_by_key(|location| {
+    (
+      target.map_or(0, |target| distance(location, &target)),
+      location.1,
+      location.0,
+    )
+  });
+
+  destinations
+}
+
+/// Play a quick turn for the current player: a random insertion, then the move closest to their
+/// target
+fn rollout_turn<R: Rng>(model: &mut Model, rng: &mut R) -> [Change; 2] {
+  let (location, rotation) = *model.board.legal_insertions().choose(rng).unwrap();
+  let inserted = model.insert_tile(location, rotation).unwrap();
+
+  let player = model.current_player;
+  let to = closest_moves(model, player)[0];
+  let moved = model.move_player(player, to).unwrap();
+
+  [inserted, moved]
+}
+
+/// How well each player is doing, from 0 for no progress to 1 for winning
+fn score(model: &Model) -> HashMap<Player, f64> {
+  let winner = match model.turn_phase {
+    TurnPhase::GameOver { winner } => Some(winner),
+    _ => None,
+  };
+
+  model
+    .players
+    .iter()
+    .map(|(player, cards)| {
+      let score = match winner {
+        Some(winner) if winner == *player => 1.0,
+        Some(_) => 0.0,
+        None => {
+          let found = cards.found_cards.len() as f64;
+          let total = found + cards.remaining() as f64 + 1.0;
+
+          // Getting closer to the next target counts for a fraction of a card
+          let closeness = match (model.board.player_location(player), target(model, *player)) {
+            (Some(at), Some(target)) => 1.0 / (1.0 + distance(&at, &target) as f64),
+            _ => 0.0,
+          };
+
+          0.9 * (found + closeness) / total
+        }
+      };
+      (*player, score)
+    })
+    .collect()
+}
+
+/// Where the player is heading: the item they are looking for, or their start once they have
+/// found everything. None if their item is on the spare tile.
+fn target(model: &Model, player: Player) -> Option<Location> {
+  let looking_for = model.players[&player].current_card;
+
+  model
+    .board
+    .placed
+    .iter()
+    .find(|(_, tile)| match looking_for {
+      Some(item) => tile.tile.marking == Some(TileMarking::Item(item)),
+      None => tile.is_start_of(player),
+    })
+    .map(|(location, _)| *location)
+}
+
+/// How far apart two locations are in a straight line
+fn distance(a: &Location, b: &Location) -> usize {
+  a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
@@ -1,46 +1,168 @@
-extern crate strum;
-use crate::board::{Board, Item, Player};
-use crate::errors::{GenericResult, WrongPlayer};
-use rand::{seq::SliceRandom, Rng};
+use crate::board::{Board, BoardLayout, Insertion, Item, Location, Player, Rotation};
+use crate::errors::{LabyrinthError, LabyrinthResult};
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use std::collections::{HashMap, HashSet};
-use strum::IntoEnumIterator;
 
-#[derive(Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum TurnPhase {
   Move,
   InsertTile,
+  GameOver { winner: Player },
 }
 
+/// How the item cards are shared out between the players
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum DealingPolicy {
+  /// Deal the whole deck equally, discarding any cards left over so every hand is the same size
+  #[default]
+  EqualHands,
+  /// Deal each player this many cards, for a shorter game
+  FixedHandSize(usize),
+  /// Deal each player one card, with the rest forming a draw pile shared by everyone
+  SharedPile,
+}
+
+/// Everything needed to set up a game.
+/// Games created from the same config always have the same board and cards.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct GameConfig {
+  pub seed: u64,
+  pub players: HashSet<Player>,
+  pub starting_player: Player,
+  /// Whether players may take back their insertions and moves
+  pub allow_undo: bool,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub dealing: DealingPolicy,
+  /// How many tiles there are along each side of the board, an odd number of at least 5
+  #[cfg_attr(feature = "serde", serde(default = "crate::board::standard_size"))]
+  pub board_size: usize,
+  /// The layout to make the board from instead of the standard one, which must be `board_size`
+  /// tiles across
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub layout: Option<BoardLayout>,
+}
+
+impl GameConfig {
+  /// Check that a game can be created from this config
+  pub fn validate(&self) -> LabyrinthResult<()> {
+    if self.players.is_empty() {
+      return Err(LabyrinthError::InvalidConfig {
+        reason: "at least one player must take part".to_string(),
+      });
+    }
+
+    if !self.players.contains(&self.starting_player) {
+      return Err(LabyrinthError::PlayerNotPlaying {
+        player: self.starting_player,
+      });
+    }
+
+    let layout = self.board_layout()?;
+    layout.validate()?;
+    if layout.size != self.board_size {
+      return Err(LabyrinthError::InvalidConfig {
+        reason: format!(
+          "the board is {} tiles across but the layout is {}",
+          self.board_size, layout.size
+        ),
+      });
+    }
+
+    let starts = layout.starts();
+    if let Some(player) = self.players.iter().find(|player| !starts.contains(player)) {
+      return Err(LabyrinthError::InvalidConfig {
+        reason: format!("there is no start for {:?} on the board", player),
+      });
+    }
+
+    let num_items = layout.items().len();
+    if num_items < self.players.len() {
+      return Err(LabyrinthError::InvalidConfig {
+        reason: format!(
+          "the board has {} items, too few to deal one to each of the {} players",
+          num_items,
+          self.players.len()
+        ),
+      });
+    }
+
+    if let DealingPolicy::FixedHandSize(cards_per_player) = self.dealing {
+      let max_cards = num_items / self.players.len();
+      if cards_per_player == 0 || cards_per_player > max_cards {
+        return Err(LabyrinthError::InvalidConfig {
+          reason: format!(
+            "each of the {} players can be dealt between 1 and {} cards, not {}",
+            self.players.len(),
+            max_cards,
+            cards_per_player
+          ),
+        });
+      }
+    }
+
+    Ok(())
+  }
+
+  /// The layout the board is made from: the one given, or else the standard one of the board's size
+  pub fn board_layout(&self) -> LabyrinthResult<BoardLayout> {
+    match &self.layout {
+      Some(layout) => Ok(layout.clone()),
+      None => BoardLayout::standard(self.board_size),
+    }
+  }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone)]
 pub struct Model {
+  pub config: GameConfig,
   pub board: Board,
   pub players: HashMap<Player, Cards>,
   pub current_player: Player,
   pub turn_phase: TurnPhase,
+  /// Cards still to be drawn when players share a draw pile, the next card last
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub draw_pile: Vec<Item>,
 }
 
 impl Model {
-  /// Create a new model with a random board and random cards dealt to each player
-  pub fn new<R: Rng>(
-    rng: &mut R,
-    players: &HashSet<Player>,
-    starting_player: Player,
-  ) -> GenericResult<Self> {
-    if !players.contains(&starting_player) {
-      return Err(Box::new(WrongPlayer::new("Starting player is not playing")));
-    }
+  /// Create a new model with a random board and random cards dealt to each player.
+  /// The board and cards are chosen using a portable random number generator seeded from the
+  /// config, so the same config always creates the same game on every platform.
+  pub fn new(config: &GameConfig) -> LabyrinthResult<Self> {
+    config.validate()?;
+
+    let players = &config.players;
+    let starting_player = config.starting_player;
 
-    let board = Board::new(rng, players);
+    let rng = &mut ChaCha8Rng::seed_from_u64(config.seed);
+
+    let board = Board::new(rng, players, &config.board_layout()?)?;
     let mut player_cards: HashMap<Player, Cards> = players
       .iter()
       .map(|player| (*player, Cards::new()))
       .collect();
 
-    // Divide the deck of items equally between the players
-    // FIXME: Currently players will end up wih a different number of cards if the number of
-    // Item enum values is not divisible by the number of players
-    let mut deck: Vec<Item> = Item::iter().collect();
+    // Only items on the board can be looked for, which on smaller boards is not all of them
+    let mut deck: Vec<Item> = board.items();
     deck.shuffle(rng);
 
+    // Every player is dealt the same number of cards, with the rest discarded or left to draw from
+    let hand_size = match config.dealing {
+      DealingPolicy::EqualHands => deck.len() / players.len(),
+      DealingPolicy::FixedHandSize(hand_size) => hand_size,
+      DealingPolicy::SharedPile => 1,
+    };
+    let draw_pile = match config.dealing {
+      DealingPolicy::SharedPile => deck.split_off(hand_size * players.len()),
+      _ => Vec::new(),
+    };
+    deck.truncate(hand_size * players.len());
+
     let mut current_player = starting_player;
     for card in deck {
       player_cards
@@ -49,21 +171,37 @@ impl Model {
         .hidden_cards
         .push(card);
 
-      current_player = next_player(&players, current_player).unwrap();
+      current_player = next_player(players, current_player).unwrap();
     }
 
-    for (_, cards) in &mut player_cards {
+    for cards in player_cards.values_mut() {
       cards.draw_next();
     }
 
     Ok(Model {
+      config: config.clone(),
       board,
       players: player_cards,
       current_player: starting_player,
       turn_phase: TurnPhase::InsertTile,
+      draw_pile,
     })
   }
 
+  /// The player has found their current card, so give them their next card to look for,
+  /// taking it from the draw pile if they have none of their own left
+  pub fn draw_next(&mut self, player: Player) {
+    let cards = self.players.get_mut(&player).unwrap();
+
+    if cards.hidden_cards.is_empty() {
+      if let Some(card) = self.draw_pile.pop() {
+        cards.hidden_cards.push(card);
+      }
+    }
+
+    cards.draw_next();
+  }
+
   pub fn current_player_cards(&self) -> &Cards {
     self.players.get(&self.current_player).unwrap()
   }
@@ -74,10 +212,318 @@ impl Model {
 
   pub fn end_turn(&mut self) {
     self.current_player =
-      next_player(&self.players.keys().cloned().collect(), self.current_player).unwrap();
+      next_player(&self.players.keys().copied().collect(), self.current_player).unwrap();
+  }
+
+  /// Every legal full turn the current player could take: where to insert the spare tile, how to
+  /// rotate it, and where to move to afterwards.
+  /// Nothing is yielded unless the current player is about to insert the tile.
+  pub fn legal_turns(&self) -> impl Iterator<Item = Turn> + '_ {
+    let player = self.current_player;
+    let insertions = if self.turn_phase == TurnPhase::InsertTile {
+      self.board.legal_insertions()
+    } else {
+      Vec::new()
+    };
+
+    insertions
+      .into_iter()
+      .flat_map(move |(insert_at, rotation)| {
+        let mut board = self.board.clone();
+        board.insert_spare(insert_at, rotation).unwrap();
+
+        let mut destinations: Vec<Location> = board
+          .player_location(&player)
+          .map(|location| board.reachable_from(&location).into_iter().collect())
+          .unwrap_or_default();
+        destinations.sort_by_key(|location| (location.1, location.0));
+
+        destinations.into_iter().map(move |move_to| Turn {
+          insert_at,
+          rotation,
+          move_to,
+        })
+      })
+  }
+
+  /// Insert the spare tile into the board, after which the current player must move.
+  /// Does not check whose turn it is or that it is time to insert the tile.
+  pub fn insert_tile(&mut self, location: Location, rotation: Rotation) -> LabyrinthResult<Change> {
+    let insertion = self.board.insert_spare(location, rotation)?;
+    self.turn_phase = TurnPhase::Move;
+
+    Ok(Change::TileInserted(insertion))
+  }
+
+  /// Move a player across the board and end their turn.
+  /// Does not check whose turn it is or that it is time to move.
+  pub fn move_player(&mut self, player: Player, location: Location) -> LabyrinthResult<Change> {
+    let from = self
+      .board
+      .player_location(&player)
+      .ok_or(LabyrinthError::PlayerNotOnBoard { player })?;
+    self.board.move_player(&player, &location)?;
+
+    let player_cards = &self.players[&player];
+    let mut found = None;
+
+    if player_cards.current_card.is_some()
+      && player_cards.current_card == self.board.item_at(&location).unwrap()
+    {
+      // Player has found the item they're looking for, draw the next item card
+      found = player_cards.current_card;
+      self.draw_next(player);
+    }
+
+    let change = Change::PlayerMoved {
+      player,
+      from,
+      found,
+    };
+
+    if self.has_won(player) {
+      self.turn_phase = TurnPhase::GameOver { winner: player };
+      return Ok(change);
+    }
+
+    // Hand over to the next player, who starts their turn by inserting the tile
+    self.end_turn();
+    self.turn_phase = TurnPhase::InsertTile;
+
+    Ok(change)
+  }
+
+  /// Play a whole turn for the current player, returning the changes it made so they can be
+  /// undone in reverse order. Nothing is changed if the turn is not legal.
+  pub fn play_turn(&mut self, turn: &Turn) -> LabyrinthResult<[Change; 2]> {
+    let inserted = self.insert_tile(turn.insert_at, turn.rotation)?;
+
+    match self.move_player(self.current_player, turn.move_to) {
+      Ok(moved) => Ok([inserted, moved]),
+      Err(error) => {
+        self.undo(&inserted);
+        Err(error)
+      }
+    }
+  }
+
+  /// Reverse a change to the game. Changes must be undone in the reverse order they were made.
+  pub fn undo(&mut self, change: &Change) {
+    match change {
+      Change::TileInserted(insertion) => {
+        self.board.undo_insertion(insertion);
+        self.turn_phase = TurnPhase::InsertTile;
+      }
+      Change::PlayerMoved {
+        player,
+        from,
+        found,
+      } => {
+        self.board.place_player(player, from).unwrap();
+        if let Some(item) = found {
+          let cards = self.players.get_mut(player).unwrap();
+          cards.undo_draw(*item);
+
+          // Cards drawn from the shared pile go back on top of it
+          if self.config.dealing == DealingPolicy::SharedPile {
+            if let Some(card) = cards.hidden_cards.pop() {
+              self.draw_pile.push(card);
+            }
+          }
+        }
+        self.current_player = *player;
+        self.turn_phase = TurnPhase::Move;
+      }
+    }
+  }
+
+  pub fn is_game_over(&self) -> bool {
+    matches!(self.turn_phase, TurnPhase::GameOver { .. })
+  }
+
+  /// A player has won once they have found all of their cards and returned to their start tile
+  pub fn has_won(&self, player: Player) -> bool {
+    let all_found = self
+      .players
+      .get(&player)
+      .is_some_and(|cards| cards.all_found());
+
+    all_found
+      && self
+        .board
+        .placed
+        .values()
+        .any(|tile| tile.players.contains(&player) && tile.is_start_of(player))
+  }
+
+  /// The final ranking of the players, winner first, then by the number of cards found
+  pub fn standings(&self) -> Vec<Standing> {
+    let winner = match self.turn_phase {
+      TurnPhase::GameOver { winner } => Some(winner),
+      _ => None,
+    };
+
+    let mut standings: Vec<Standing> = self
+      .players
+      .iter()
+      .map(|(player, cards)| Standing {
+        player: *player,
+        found: cards.found_cards.len() as u32,
+        remaining: cards.remaining() as u32,
+      })
+      .collect();
+
+    standings.sort_by_key(|standing| {
+      (
+        Some(standing.player) != winner,
+        std::cmp::Reverse(standing.found),
+        standing.player as u8,
+      )
+    });
+
+    standings
+  }
+
+  /// Check that the game is in a state the rules allow, to catch bugs when simulating games
+  pub fn check_invariants(&self) -> LabyrinthResult<()> {
+    let broken = |reason: String| Err(LabyrinthError::BrokenInvariant { reason });
+
+    self.board.validate()?;
+    self.board.check_graph()?;
+
+    if self.board.size() != self.config.board_size {
+      return broken(format!(
+        "the board is {} tiles across, but the game was set up for {}",
+        self.board.size(),
+        self.config.board_size
+      ));
+    }
+
+    if !self.players.contains_key(&self.current_player) {
+      return broken(format!(
+        "it is the turn of {:?}, who is not playing",
+        self.current_player
+      ));
+    }
+
+    for player in self.players.keys() {
+      let tiles = self
+        .board
+        .placed
+        .values()
+        .filter(|tile| tile.players.contains(player))
+        .count();
+      if tiles != 1 {
+        return broken(format!("{:?} is on {} tiles", player, tiles));
+      }
+    }
+
+    for (location, tile) in &self.board.placed {
+      if let Some(player) = tile.players.iter().find(|p| !self.players.contains_key(p)) {
+        return broken(format!("{:?} is on {} but not playing", player, location));
+      }
+    }
+
+    // Every card is held, found or waiting to be drawn by exactly one player, and is on the board
+    let on_board = self.board.items();
+    let mut seen = HashSet::new();
+    let cards = self
+      .players
+      .values()
+      .flat_map(|cards| {
+        cards
+          .current_card
+          .iter()
+          .chain(&cards.hidden_cards)
+          .chain(&cards.found_cards)
+      })
+      .chain(&self.draw_pile);
+    for card in cards {
+      if !seen.insert(*card) {
+        return broken(format!("{} has been dealt more than once", card));
+      }
+      if !on_board.contains(card) {
+        return broken(format!("{} has been dealt but is not on the board", card));
+      }
+    }
+
+    if let TurnPhase::GameOver { winner } = self.turn_phase {
+      if !self.has_won(winner) {
+        return broken(format!("{:?} won without finishing", winner));
+      }
+    }
+
+    Ok(())
   }
 }
 
+#[cfg(feature = "serde")]
+impl Model {
+  /// Save the whole game as JSON
+  pub fn save_json(&self) -> LabyrinthResult<String> {
+    serde_json::to_string(self).map_err(|error| LabyrinthError::InvalidSave {
+      reason: error.to_string(),
+    })
+  }
+
+  /// Load a game saved with `save_json`, checking that it is a valid game before accepting it
+  pub fn load_json(json: &str) -> LabyrinthResult<Self> {
+    let mut model: Model =
+      serde_json::from_str(json).map_err(|error| LabyrinthError::InvalidSave {
+        reason: error.to_string(),
+      })?;
+
+    model.board.validate()?;
+    if !model.players.contains_key(&model.current_player) {
+      return Err(LabyrinthError::PlayerNotPlaying {
+        player: model.current_player,
+      });
+    }
+    for player in model.players.keys() {
+      if model.board.player_location(player).is_none() {
+        return Err(LabyrinthError::PlayerNotOnBoard { player: *player });
+      }
+    }
+
+    model.board.rebuild_graph();
+    // The board alone cannot tell whether the cards dealt match its items
+    model.check_invariants()?;
+
+    Ok(model)
+  }
+}
+
+/// A change made to the game by a player, with everything needed to undo it
+#[derive(Debug, Clone)]
+pub enum Change {
+  TileInserted(Insertion),
+  PlayerMoved {
+    player: Player,
+    from: Location,
+    /// The card the player found by moving, if any
+    found: Option<Item>,
+  },
+}
+
+/// A full turn: inserting the spare tile with a rotation, then moving the current player
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Turn {
+  pub insert_at: Location,
+  pub rotation: Rotation,
+  pub move_to: Location,
+}
+
+/// How well a player did in the game
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone)]
+pub struct Standing {
+  pub player: Player,
+  pub found: u32,
+  pub remaining: u32,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 pub struct Cards {
   pub current_card: Option<Item>,
   pub hidden_cards: Vec<Item>,
@@ -101,9 +547,30 @@ impl Cards {
 
     self.current_card = self.hidden_cards.pop();
   }
+
+  /// Put a found card back as the current card, returning the card drawn after it to the top of
+  /// the hidden cards
+  pub fn undo_draw(&mut self, found: Item) {
+    if let Some(current_card) = self.current_card.take() {
+      self.hidden_cards.push(current_card);
+    }
+
+    self.found_cards.remove(&found);
+    self.current_card = Some(found);
+  }
+
+  /// The number of cards still to be found, including the current card
+  pub fn remaining(&self) -> usize {
+    self.hidden_cards.len() + self.current_card.map_or(0, |_| 1)
+  }
+
+  /// Whether every card has been found, meaning the player must now return to their start
+  pub fn all_found(&self) -> bool {
+    self.current_card.is_none() && self.hidden_cards.is_empty()
+  }
 }
 
-pub fn next_player(players: &HashSet<Player>, current_player: Player) -> GenericResult<Player> {
+pub fn next_player(players: &HashSet<Player>, current_player: Player) -> LabyrinthResult<Player> {
   let mut player = current_player;
 
   loop {
@@ -119,7 +586,276 @@ pub fn next_player(players: &HashSet<Player>, current_player: Player) -> Generic
     }
 
     if player == current_player {
-      return Err(Box::new(WrongPlayer::new("Unable to find next player")));
+      return Err(LabyrinthError::PlayerNotPlaying {
+        player: current_player,
+      });
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board::{Tile, TileMarking};
+  use strum::IntoEnumIterator;
+
+  // Games must be the same for the same seed in every release, so that saved seeds and logs can
+  // be played again. Only change these boards and deals when changing how games are set up on
+  // purpose.
+
+  fn config(seed: u64, board_size: usize, dealing: DealingPolicy) -> GameConfig {
+    GameConfig {
+      seed,
+      players: HashSet::from([Player::Player1, Player::Player2, Player::Player3]),
+      starting_player: Player::Player1,
+      allow_undo: true,
+      dealing,
+      board_size,
+      layout: None,
+    }
+  }
+
+  /// Each row of the board, as one hex digit per tile for its paths (up 1, right 2, down 4,
+  /// left 8) then one character per tile for its marking: a letter for an item, a number for a
+  /// start
+  fn board_rows(model: &Model) -> Vec<String> {
+    let size = model.board.size();
+    (0..size)
+      .map(|y| {
+        let tiles: Vec<_> = (0..size)
+          .map(|x| &model.board.placed[&Location(x, y)])
+          .collect();
+        let paths: String = tiles.iter().map(|tile| paths(&Tile::from(*tile))).collect();
+        let markings: String = tiles.iter().map(|tile| marking(&tile.tile)).collect();
+        format!("{} {}", paths, markings)
+      })
+      .collect()
+  }
+
+  fn paths(tile: &Tile) -> char {
+    let bits = tile.path_up as u32
+      | (tile.path_right as u32) << 1
+      | (tile.path_down as u32) << 2
+      | (tile.path_left as u32) << 3;
+    char::from_digit(bits, 16).unwrap()
+  }
+
+  fn marking(tile: &Tile) -> char {
+    match tile.marking {
+      None => '.',
+      Some(TileMarking::Item(item)) => {
+        let index = Item::iter().position(|other| other == item).unwrap();
+        (b'a' + index as u8) as char
+      }
+      Some(TileMarking::PlayerStart(player)) => char::from_digit(player as u32 + 1, 10).unwrap(),
     }
   }
+
+  /// The spare tile, then each player's cards in the order they look for them, then the draw pile
+  fn deal(model: &Model) -> Vec<String> {
+    let spare = format!(
+      "spare {}{}",
+      paths(&model.board.spare),
+      marking(&model.board.spare)
+    );
+    let players = [
+      Player::Player1,
+      Player::Player2,
+      Player::Player3,
+      Player::Player4,
+    ];
+    let hands = players
+      .into_iter()
+      .filter_map(|player| model.players.get(&player).map(|cards| (player, cards)))
+      .map(|(player, cards)| {
+        let hand: Vec<_> = cards
+          .current_card
+          .iter()
+          .chain(cards.hidden_cards.iter().rev())
+          .map(|item| item.to_string())
+          .collect();
+        format!("{:?} {}", player, hand.join(" "))
+      });
+
+    let pile: Vec<_> = std::iter::once("pile".to_string())
+      .chain(model.draw_pile.iter().rev().map(|item| item.to_string()))
+      .collect();
+
+    std::iter::once(spare)
+      .chain(hands)
+      .chain(std::iter::once(pile.join(" ")))
+      .collect()
+  }
+
+  #[test]
+  fn seed_1_deals_equal_hands_on_a_board_5_tiles_across() {
+    let model = Model::new(&config(1, 5, DealingPolicy::EqualHands)).unwrap();
+    assert_eq!(
+      board_rows(&model),
+      [
+        "66e6c 1.af2",
+        "39ab5 j..k.",
+        "7a79d b.chd",
+        "6ba35 .g...",
+        "33ba9 3.e.4",
+      ]
+    );
+    assert_eq!(
+      deal(&model),
+      [
+        "spare bi",
+        "Player1 Gnome Cat Crown",
+        "Player2 Spider Chest Book",
+        "Player3 Keys Unicorn Ghost",
+        "pile",
+      ]
+    );
+  }
+
+  #[test]
+  fn seed_2_deals_hands_of_3_on_a_board_7_tiles_across() {
+    let model = Model::new(&config(2, 7, DealingPolicy::FixedHandSize(3))).unwrap();
+    assert_eq!(
+      board_rows(&model),
+      [
+        "6cece6c 1.m.lj2",
+        "775535c ut....n",
+        "737eead v.hcs.w",
+        "3a3cc3c g....qx",
+        "7ebadbd iek.adf",
+        "3aaad35 r...b..",
+        "33bab99 3.p.o.4",
+      ]
+    );
+    assert_eq!(
+      deal(&model),
+      [
+        "spare 5.",
+        "Player1 Bat Potion Ring",
+        "Player2 Book Unicorn Candle",
+        "Player3 Keys Sack Gem",
+        "pile",
+      ]
+    );
+  }
+
+  #[test]
+  fn seed_3_deals_a_shared_pile_on_a_board_7_tiles_across() {
+    let model = Model::new(&config(3, 7, DealingPolicy::SharedPile)).unwrap();
+    assert_eq!(
+      board_rows(&model),
+      [
+        "66ece6c 1.m.l.2",
+        "56c396c .j.g.rn",
+        "7e7be5d vehds.w",
+        "355c55d ......c",
+        "73b9dad i.kqa.f",
+        "56acadd ...x.ut",
+        "39bbba9 3.pbo.4",
+      ]
+    );
+    assert_eq!(
+      deal(&model),
+      [
+        "spare 5.",
+        "Player1 Bat",
+        "Player2 Sack",
+        "Player3 Book",
+        "pile Gnome Crown Genie Sword Lizard Candle Ring Unicorn Owl Chest Dragon Cat Mouse Potion Beetle Spider Helmet Gem Keys Ghost Goblet",
+      ]
+    );
+  }
+
+  #[test]
+  fn seed_4_deals_a_shared_pile_on_a_board_9_tiles_across() {
+    let model = Model::new(&config(4, 9, DealingPolicy::SharedPile)).unwrap();
+    assert_eq!(
+      board_rows(&model),
+      [
+        "6aeceaeec 1.a.b.c.2",
+        "56b365dd5 .v.......",
+        "7673eeebd d.e.f.g.h",
+        "569335669 .........",
+        "7c7c76d5d ixj.k.l.m",
+        "33b5a57d9 .......w.",
+        "7abcb6d7d n.o.p.q.r",
+        "7aaa65735 .........",
+        "39bab9b99 3.s.t.u.4",
+      ]
+    );
+    assert_eq!(
+      deal(&model),
+      [
+        "spare 3.",
+        "Player1 Bat",
+        "Player2 Sword",
+        "Player3 Book",
+        "pile Crown Spider Cat Potion Goblet Chest Sack Dragon Beetle Lizard Genie Candle Helmet Mouse Gnome Owl Gem Keys Unicorn Ghost Ring",
+      ]
+    );
+  }
+
+  #[test]
+  fn seed_5_deals_equal_hands_on_a_board_11_tiles_across() {
+    let model = Model::new(&config(5, 11, DealingPolicy::EqualHands)).unwrap();
+    assert_eq!(
+      board_rows(&model),
+      [
+        "6aeceaeaeac 1.a.b.c.d.2",
+        "365dcc95ad9 ...........",
+        "7376eaece9d e.f.g.h.i.j",
+        "593393ad59d ...........",
+        "737a7de6ddd k.l.m.n.o.p",
+        "3dc739a6c95 ...........",
+        "767bb5d5d3d q.r.s.t.u.v",
+        "53aa335a759 ...........",
+        "75b5b3b5ddd w.x........",
+        "5d3cb953795 ...........",
+        "3bb9b3b3ba9 3.........4",
+      ]
+    );
+    assert_eq!(
+      deal(&model),
+      [
+        "spare 5.",
+        "Player1 Owl Candle Book Ring Goblet Beetle Unicorn Mouse",
+        "Player2 Bat Spider Dragon Keys Sword Lizard Ghost Potion",
+        "Player3 Genie Crown Chest Gem Helmet Gnome Cat Sack",
+        "pile",
+      ]
+    );
+  }
+
+  #[test]
+  fn seed_6_deals_hands_of_2_on_a_board_13_tiles_across() {
+    let model = Model::new(&config(6, 13, DealingPolicy::FixedHandSize(2))).unwrap();
+    assert_eq!(
+      board_rows(&model),
+      [
+        "6ceaeeeeececc 1.a.b.c.d.e.2",
+        "3936a65553555 .............",
+        "767ce7e3e9e9d f.g.h.i.j.k.l",
+        "3b69ca363d569 .............",
+        "737c7beaecd3d m.n.o.p.q.r.s",
+        "6e565355953ed .............",
+        "7373797ed6d6d t.u.v.w.x....",
+        "5a575aceba57c .............",
+        "757ababadad3d .............",
+        "3e7a96aaa5575 .............",
+        "79bdb5b5b3d9d .............",
+        "5c5ac76366a3d .............",
+        "39bbbab9bbb99 3...........4",
+      ]
+    );
+    assert_eq!(
+      deal(&model),
+      [
+        "spare 3.",
+        "Player1 Genie Candle",
+        "Player2 Gnome Mouse",
+        "Player3 Keys Spider",
+        "pile",
+      ]
+    );
+  }
 }
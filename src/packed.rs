@@ -0,0 +1,526 @@
+use crate::board::{Board, Location, PlacedTile, Player, Rotation, Tile, TileMarking};
+use crate::errors::{LabyrinthError, LabyrinthResult};
+use std::collections::{HashMap, HashSet};
+use strum::IntoEnumIterator;
+
+/// The most tiles there can be along each side of a packed board, so that a bitboard fits every
+/// tile on the board
+pub const MAX_SIZE: usize = 11;
+const MAX_CELLS: usize = MAX_SIZE * MAX_SIZE;
+
+const PATH_UP: u8 = 1;
+const PATH_RIGHT: u8 = 2;
+const PATH_DOWN: u8 = 4;
+const PATH_LEFT: u8 = 8;
+const PATHS: u8 = 0b1111;
+/// How far round a tile has been turned is kept in the bits above its paths
+const ROTATION_SHIFT: u8 = 4;
+
+const PLAYERS: [Player; 4] = [
+  Player::Player1,
+  Player::Player2,
+  Player::Player3,
+  Player::Player4,
+];
+
+const ROTATIONS: [Rotation; 4] = [
+  Rotation::Zero,
+  Rotation::Clockwise90,
+  Rotation::Clockwise180,
+  Rotation::Clockwise270,
+];
+
+/// Marks a pawn that is not on the board, or that no insertion is forbidden
+const NONE: u8 = u8::MAX;
+
+/// A compact copy of a board, cheap to copy and change, for bots that search through many turns.
+///
+/// Boards can be up to `MAX_SIZE` tiles across. Cells are numbered in rows from the top left, so
+/// on a board `size` tiles across the tile at `Location(x, y)` is cell `y * size + x`. Each tile is a byte: the low 4 bits are the sides it opens on as it lies on the
+/// board, and the 2 bits above are how far it has been turned. Sets of cells, such as everywhere a
+/// pawn can reach, are bitboards with bit `i` set for cell `i`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PackedBoard {
+  /// How many tiles there are along each side of the board
+  size: u8,
+  /// The sides each tile opens on, up 1, right 2, down 4 and left 8, and its rotation
+  tiles: [u8; MAX_CELLS],
+  markings: [Option<TileMarking>; MAX_CELLS],
+  /// The cell each player's pawn is on, indexed by player
+  pawns: [u8; 4],
+  /// The sides the spare tile opens on before it is rotated
+  spare: u8,
+  spare_marking: Option<TileMarking>,
+  /// The cell the last tile was pushed out from, where the spare cannot be inserted next
+  forbidden: u8,
+}
+
+/// How the spare tile was inserted into a packed board, with everything needed to undo it
+#[derive(Debug, Copy, Clone)]
+pub struct PackedInsertion {
+  insert_at: u8,
+  /// The rotation of the tile that was pushed off the board and became the spare tile
+  pushed_out_rotation: Rotation,
+  previous_forbidden: u8,
+}
+
+impl PackedBoard {
+  /// How many tiles there are along each side of the board
+  pub fn size(&self) -> usize {
+    self.size as usize
+  }
+
+  fn index(&self, x: usize, y: usize) -> usize {
+    y * self.size() + x
+  }
+
+  fn checked_index(&self, at: &Location) -> LabyrinthResult<usize> {
+    if at.0 < self.size() && at.1 < self.size() {
+      Ok(self.index(at.0, at.1))
+    } else {
+      Err(LabyrinthError::from(at))
+    }
+  }
+
+  fn location(&self, cell: usize) -> Location {
+    Location(cell % self.size(), cell / self.size())
+  }
+
+  /// The locations of every cell in a bitboard
+  pub fn locations(&self, cells: u128) -> impl Iterator<Item = Location> + '_ {
+    (0..self.size() * self.size())
+      .filter(move |cell| cells & 1 << cell != 0)
+      .map(|cell| self.location(cell))
+  }
+
+  /// The tiles along the line a tile inserted at `insert_at` slides, starting from the insertion
+  /// and ending with the tile that is pushed out. Only the first `size` are used.
+  /// Returns None if a tile cannot be inserted there.
+  fn line_of(&self, insert_at: &Location) -> Option<[usize; MAX_SIZE]> {
+    let last = self.size() - 1;
+    let cells = |cell: &dyn Fn(usize) -> usize| {
+      let mut line = [0; MAX_SIZE];
+      for (i, at) in line.iter_mut().enumerate().take(self.size()) {
+        *at = cell(i);
+      }
+      line
+    };
+
+    match *insert_at {
+      Location(x, 0) if x % 2 == 1 && x < last => Some(cells(&|i| self.index(x, i))),
+      Location(x, y) if x == last && y % 2 == 1 && y < last => {
+        Some(cells(&|i| self.index(last - i, y)))
+      }
+      Location(x, y) if y == last && x % 2 == 1 && x < last => {
+        Some(cells(&|i| self.index(x, last - i)))
+      }
+      Location(0, y) if y % 2 == 1 && y < last => Some(cells(&|i| self.index(i, y))),
+      _ => None,
+    }
+  }
+
+  /// Whether a tile opening on `paths` can lie at a location without a path leading off the board
+  fn placement_ok(&self, location: &Location, paths: u8) -> bool {
+    let last = self.size() - 1;
+    let off_board = [
+      (location.1 == 0, PATH_UP),
+      (location.0 == last, PATH_RIGHT),
+      (location.1 == last, PATH_DOWN),
+      (location.0 == 0, PATH_LEFT),
+    ];
+
+    off_board
+      .into_iter()
+      .all(|(on_edge, path)| !on_edge || paths & path == 0)
+  }
+
+  /// Pairs of cells joined by a path: bit `i` of the first is set if cell `i` is joined to the
+  /// cell on its right, and of the second if it is joined to the cell below it
+  fn joins(&self) -> (u128, u128) {
+    let size = self.size();
+    let cells = size * size;
+    let mut right = 0;
+    let mut down = 0;
+
+    for (i, tile) in self.tiles.iter().enumerate().take(cells) {
+      if tile & PATH_RIGHT != 0 && i % size != size - 1 && self.tiles[i + 1] & PATH_LEFT != 0 {
+        right |= 1 << i;
+      }
+      if tile & PATH_DOWN != 0 && i + size < cells && self.tiles[i + size] & PATH_UP != 0 {
+        down |= 1 << i;
+      }
+    }
+
+    (right, down)
+  }
+
+  /// Every cell that can be reached by following paths from a location, including itself, as a
+  /// bitboard. Spreads out one step in every direction at once until it stops growing.
+  pub fn reachable(&self, from: &Location) -> LabyrinthResult<u128> {
+    let (right, down) = self.joins();
+    let size = self.size();
+    let mut reached = 1 << self.checked_index(from)?;
+
+    loop {
+      let grown = reached
+        | (reached & right) << 1
+        | (reached >> 1) & right
+        | (reached & down) << size
+        | (reached >> size) & down;
+
+      if grown == reached {
+        return Ok(reached);
+      }
+      reached = grown;
+    }
+  }
+
+  /// Whether there is a path between two locations
+  pub fn is_connected(&self, from: &Location, to: &Location) -> LabyrinthResult<bool> {
+    Ok(self.reachable(from)? & 1 << self.checked_index(to)? != 0)
+  }
+
+  /// Where the player's pawn is, if they are playing
+  pub fn pawn(&self, player: Player) -> Option<Location> {
+    match self.pawns[player as usize] {
+      NONE => None,
+      cell => Some(self.location(cell as usize)),
+    }
+  }
+
+  /// The marking on the tile at a location
+  pub fn marking_at(&self, at: &Location) -> LabyrinthResult<Option<TileMarking>> {
+    Ok(self.markings[self.checked_index(at)?])
+  }
+
+  /// Move a player's pawn, checking there is a path to where it is moving
+  pub fn move_pawn(&mut self, player: Player, to: &Location) -> LabyrinthResult<()> {
+    let from = self
+      .pawn(player)
+      .ok_or(LabyrinthError::PlayerNotOnBoard { player })?;
+
+    if !self.is_connected(&from, to)? {
+      return Err(LabyrinthError::NoPath { from, to: *to });
+    }
+    self.pawns[player as usize] = self.index(to.0, to.1) as u8;
+
+    Ok(())
+  }
+
+  /// Put a player's pawn on a tile without checking there is a path to it, e.g. to undo a move
+  pub fn place_pawn(&mut self, player: Player, at: &Location) -> LabyrinthResult<()> {
+    self.pawns[player as usize] = self.checked_index(at)? as u8;
+    Ok(())
+  }
+
+  /// Every insertion location and rotation of the spare tile allowed this turn, the same as
+  /// `Board::legal_insertions`
+  pub fn legal_insertions(&self) -> Vec<(Location, Rotation)> {
+    let last = self.size() - 1;
+
+    (1..last)
+      .step_by(2)
+      .flat_map(|i| {
+        [
+          Location(i, 0),
+          Location(last, i),
+          Location(i, last),
+          Location(0, i),
+        ]
+      })
+      .filter(|location| self.forbidden as usize != self.index(location.0, location.1))
+      .flat_map(|location| {
+        let mut rotated: Vec<u8> = Vec::new();
+
+        Rotation::iter()
+          .filter(|rotation| {
+            let paths = rotate(self.spare, *rotation);
+            if !self.placement_ok(&location, paths) || rotated.contains(&paths) {
+              return false;
+            }
+
+            rotated.push(paths);
+            true
+          })
+          .map(move |rotation| (location, rotation))
+          .collect::<Vec<_>>()
+      })
+      .collect()
+  }
+
+  /// Insert the spare tile, sliding the row or column along and pushing the tile at the far end
+  /// out to become the spare. Follows the same rules as `Board::insert_spare`.
+  pub fn insert_spare(
+    &mut self,
+    insert_at: Location,
+    rotation: Rotation,
+  ) -> LabyrinthResult<PackedInsertion> {
+    let line = self
+      .line_of(&insert_at)
+      .ok_or(LabyrinthError::InvalidInsertion {
+        location: insert_at,
+      })?;
+
+    if self.forbidden as usize == line[0] {
+      return Err(LabyrinthError::ReversedInsertion {
+        location: insert_at,
+      });
+    }
+
+    if !self.placement_ok(&insert_at, rotate(self.spare, rotation)) {
+      return Err(LabyrinthError::InvalidRotation {
+        location: insert_at,
+        rotation,
+      });
+    }
+
+    let previous_forbidden = self.forbidden;
+    let pushed_out_rotation = self.slide(&line[..self.size()], rotation);
+
+    Ok(PackedInsertion {
+      insert_at: line[0] as u8,
+      pushed_out_rotation,
+      previous_forbidden,
+    })
+  }
+
+  /// Reverse an insertion of the spare tile, pushing the tile that was pushed out back in along
+  /// with any pawns that were carried across
+  pub fn undo_insertion(&mut self, insertion: &PackedInsertion) {
+    let insert_at = self.location(insertion.insert_at as usize);
+    let mut line = self.line_of(&insert_at).unwrap();
+    let line = &mut line[..self.size()];
+    line.reverse();
+
+    self.slide(line, insertion.pushed_out_rotation);
+    self.forbidden = insertion.previous_forbidden;
+  }
+
+  /// Slide every tile along the line one place, putting the spare in at the start with the given
+  /// rotation and taking the last one out as the spare.
+  /// Pawns on the tile pushed out go round to the tile put in.
+  /// Returns the rotation of the tile that was pushed out.
+  fn slide(&mut self, line: &[usize], rotation: Rotation) -> Rotation {
+    let pushed_out = line[line.len() - 1];
+    let (pushed_out_paths, pushed_out_rotation) = unpack(self.tiles[pushed_out]);
+    let pushed_out_marking = self.markings[pushed_out];
+
+    for i in (1..line.len()).rev() {
+      self.tiles[line[i]] = self.tiles[line[i - 1]];
+      self.markings[line[i]] = self.markings[line[i - 1]];
+    }
+    self.tiles[line[0]] = pack(self.spare, rotation);
+    self.markings[line[0]] = self.spare_marking;
+
+    for pawn in self.pawns.iter_mut().filter(|pawn| **pawn != NONE) {
+      if let Some(i) = line.iter().position(|cell| *cell == *pawn as usize) {
+        *pawn = line[(i + 1) % line.len()] as u8;
+      }
+    }
+
+    self.spare = pushed_out_paths;
+    self.spare_marking = pushed_out_marking;
+    self.forbidden = pushed_out as u8;
+
+    pushed_out_rotation
+  }
+}
+
+/// Fails if the board is more than `MAX_SIZE` tiles across
+impl TryFrom<&Board> for PackedBoard {
+  type Error = LabyrinthError;
+
+  fn try_from(board: &Board) -> LabyrinthResult<Self> {
+    if board.size() > MAX_SIZE {
+      return Err(LabyrinthError::InvalidBoard {
+        reason: format!(
+          "Only boards up to {} tiles across can be packed, not {}",
+          MAX_SIZE,
+          board.size()
+        ),
+      });
+    }
+
+    let mut packed = PackedBoard {
+      size: board.size() as u8,
+      tiles: [0; MAX_CELLS],
+      markings: [None; MAX_CELLS],
+      pawns: [NONE; 4],
+      spare: path_mask(&board.spare),
+      spare_marking: board.spare.marking,
+      forbidden: NONE,
+    };
+    packed.forbidden = board
+      .forbidden_insertion()
+      .map_or(NONE, |location| packed.index(location.0, location.1) as u8);
+
+    for (location, placed) in &board.placed {
+      let cell = packed.index(location.0, location.1);
+      packed.tiles[cell] = pack(path_mask(&placed.tile), placed.rotation);
+      packed.markings[cell] = placed.tile.marking;
+
+      for player in &placed.players {
+        packed.pawns[*player as usize] = cell as u8;
+      }
+    }
+
+    Ok(packed)
+  }
+}
+
+impl From<&PackedBoard> for Board {
+  fn from(packed: &PackedBoard) -> Self {
+    let placed: HashMap<Location, PlacedTile> = (0..packed.size() * packed.size())
+      .map(|cell| {
+        let players: HashSet<Player> = PLAYERS
+          .into_iter()
+          .filter(|player| packed.pawns[*player as usize] as usize == cell)
+          .collect();
+
+        let (paths, rotation) = unpack(packed.tiles[cell]);
+        let placed = PlacedTile {
+          tile: tile(paths, packed.markings[cell]),
+          rotation,
+          players,
+        };
+        (packed.location(cell), placed)
+      })
+      .collect();
+
+    let forbidden = match packed.forbidden {
+      NONE => None,
+      cell => Some(packed.location(cell as usize)),
+    };
+
+    Board::from_placed(
+      packed.size(),
+      placed,
+      tile(packed.spare, packed.spare_marking),
+      forbidden,
+    )
+  }
+}
+
+/// The sides a tile opens on, before it is rotated
+fn path_mask(tile: &Tile) -> u8 {
+  [
+    (tile.path_up, PATH_UP),
+    (tile.path_right, PATH_RIGHT),
+    (tile.path_down, PATH_DOWN),
+    (tile.path_left, PATH_LEFT),
+  ]
+  .into_iter()
+  .filter(|(open, _)| *open)
+  .fold(0, |mask, (_, path)| mask | path)
+}
+
+fn tile(paths: u8, marking: Option<TileMarking>) -> Tile {
+  Tile {
+    marking,
+    path_up: paths & PATH_UP != 0,
+    path_right: paths & PATH_RIGHT != 0,
+    path_down: paths & PATH_DOWN != 0,
+    path_left: paths & PATH_LEFT != 0,
+  }
+}
+
+/// Turn a tile's paths clockwise, each quarter turn moving every opening round by one side
+fn rotate(paths: u8, rotation: Rotation) -> u8 {
+  let turns = rotation as u8;
+  ((paths << turns) | (paths >> (4 - turns))) & PATHS
+}
+
+/// A tile as it lies on the board, from its paths before it is rotated
+fn pack(paths: u8, rotation: Rotation) -> u8 {
+  rotate(paths, rotation) | (rotation as u8) << ROTATION_SHIFT
+}
+
+/// A tile's paths before it was rotated, and its rotation
+fn unpack(tile: u8) -> (u8, Rotation) {
+  let rotation = ROTATIONS[(tile >> ROTATION_SHIFT) as usize];
+  let unturned = rotate(tile & PATHS, ROTATIONS[(4 - rotation as usize) % 4]);
+
+  (unturned, rotation)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board::BoardLayout;
+  use rand::seq::{IteratorRandom, SliceRandom};
+  use rand::{Rng, SeedableRng};
+  use rand_chacha::ChaCha8Rng;
+
+  /// A packed board must follow the same rules as the board it was packed from: the same
+  /// insertions allowed, the same tiles and pawns after inserting and undoing, and the same places
+  /// reachable
+  #[test]
+  fn packed_boards_play_the_same_as_boards_after_random_insertions_and_moves() {
+    let players = HashSet::from(PLAYERS);
+
+    for size in (5..=MAX_SIZE).step_by(2) {
+      let mut rng = ChaCha8Rng::seed_from_u64(size as u64);
+      let layout = BoardLayout::standard(size).unwrap();
+      let mut board = Board::new(&mut rng, &players, &layout).unwrap();
+      let mut packed = PackedBoard::try_from(&board).unwrap();
+      let locations: Vec<Location> = board.locations().collect();
+      let mut insertions = Vec::new();
+
+      let insertion_locations = board.insertion_locations();
+      for location in &locations {
+        assert_eq!(
+          packed.line_of(location).is_some(),
+          insertion_locations.contains(location),
+          "size {}, insert at {}",
+          size,
+          location
+        );
+      }
+
+      for _ in 0..500 {
+        let legal: HashSet<_> = board.legal_insertions().into_iter().collect();
+        let packed_legal: HashSet<_> = packed.legal_insertions().into_iter().collect();
+        assert_eq!(packed_legal, legal, "size {}", size);
+
+        // Sometimes take back the last insertion, and sometimes move a pawn so that pawns are
+        // carried across the edge when their line slides
+        if !insertions.is_empty() && rng.gen_ratio(1, 4) {
+          let (insertion, packed_insertion) = insertions.pop().unwrap();
+          board.undo_insertion(&insertion);
+          packed.undo_insertion(&packed_insertion);
+        } else if rng.gen_ratio(1, 4) {
+          let player = *PLAYERS.choose(&mut rng).unwrap();
+          let from = board.player_location(&player).unwrap();
+          let to = board
+            .reachable_from(&from)
+            .into_iter()
+            .choose(&mut rng)
+            .unwrap();
+          board.move_player(&player, &to).unwrap();
+          packed.move_pawn(player, &to).unwrap();
+        } else {
+          let (insert_at, rotation) = *board.legal_insertions().choose(&mut rng).unwrap();
+          let insertion = board.insert_spare(insert_at, rotation).unwrap();
+          let packed_insertion = packed.insert_spare(insert_at, rotation).unwrap();
+          insertions.push((insertion, packed_insertion));
+        }
+
+        assert_eq!(
+          PackedBoard::try_from(&board).unwrap(),
+          packed,
+          "size {}",
+          size
+        );
+        let from = *locations.choose(&mut rng).unwrap();
+        let reachable: HashSet<_> = packed.locations(packed.reachable(&from).unwrap()).collect();
+        assert_eq!(
+          reachable,
+          board.reachable_from(&from),
+          "size {}, from {}",
+          size,
+          from
+        );
+      }
+    }
+  }
+}
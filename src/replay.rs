@@ -0,0 +1,83 @@
+use crate::board::Player;
+use crate::controller::{apply_command, Command, History};
+#[cfg(feature = "serde")]
+use crate::errors::LabyrinthError;
+use crate::errors::LabyrinthResult;
+use crate::model::{GameConfig, Model};
+use std::time::SystemTime;
+
+/// A command that was accepted by the controller
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+  /// Events are numbered from 1 in the order they were accepted
+  pub number: u32,
+  pub timestamp: SystemTime,
+  pub sent_by: Player,
+  pub command: Command,
+}
+
+/// Everything needed to reproduce a game: how it was set up and every command that changed it
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct GameLog {
+  pub config: GameConfig,
+  pub events: Vec<LogEvent>,
+}
+
+impl GameLog {
+  pub fn new(config: GameConfig) -> Self {
+    GameLog {
+      config,
+      events: Vec::new(),
+    }
+  }
+
+  /// Add an accepted command to the end of the log
+  pub fn record(&mut self, sent_by: Player, command: Command) {
+    self.events.push(LogEvent {
+      number: self.events.len() as u32 + 1,
+      timestamp: SystemTime::now(),
+      sent_by,
+      command,
+    });
+  }
+}
+
+#[cfg(feature = "serde")]
+impl GameLog {
+  /// Save the log as JSON
+  pub fn save_json(&self) -> LabyrinthResult<String> {
+    serde_json::to_string(self).map_err(|error| LabyrinthError::InvalidSave {
+      reason: error.to_string(),
+    })
+  }
+
+  /// Load a log saved with `save_json`
+  pub fn load_json(json: &str) -> LabyrinthResult<Self> {
+    let log: GameLog = serde_json::from_str(json).map_err(|error| LabyrinthError::InvalidSave {
+      reason: error.to_string(),
+    })?;
+    log.config.validate()?;
+
+    Ok(log)
+  }
+}
+
+/// Recreate the game by re-applying every event in the log to a new game
+pub fn replay(log: &GameLog) -> LabyrinthResult<Model> {
+  replay_to(log, log.events.len())
+}
+
+/// Recreate the game as it was after the first `num_events` events in the log.
+/// Useful for stepping forwards and backwards through a game.
+pub fn replay_to(log: &GameLog, num_events: usize) -> LabyrinthResult<Model> {
+  let mut model = Model::new(&log.config)?;
+  let mut history = History::default();
+
+  for event in log.events.iter().take(num_events) {
+    apply_command(&mut model, &mut history, event.sent_by, &event.command)?;
+  }
+
+  Ok(model)
+}
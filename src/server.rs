@@ -0,0 +1,312 @@
+//! Play a game over TCP, with each client that connects taking a player's seat.
+//!
+//! Clients and the server send each other one JSON message per line. Clients send [`Command`]s,
+//! e.g. `"NoOp"` to look at the game, `{"InsertTile":[[1,0],"Clockwise90"]}` or
+//! `{"MovePlayer":["Player1",[1,2]]}`. The server sends [`ServerMessage`]s: which player the client
+//! is playing as soon as it connects, then what that player can see of the game after each of
+//! their commands and whenever anyone else changes the game, or an error if a command was not
+//! allowed.
+//!
+//! With the `websocket` feature, the same messages can be sent over WebSockets, see the `gateway`
+//! module.
+
+use crate::board::Player;
+use crate::bot::{run_bot, send, BotKind};
+use crate::controller::{Command, CommandRequest, Snapshot, Viewer};
+use crate::errors::{LabyrinthError, LabyrinthResult};
+use crate::events::Subscriptions;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+/// A message from the server to a client
+#[derive(serde::Serialize, serde::Deserialize)]
+pub enum ServerMessage {
+  /// Sent once as soon as the client connects, saying which player they are playing
+  Seated { player: Player },
+  /// What the client's player can see of the game
+  Snapshot(Box<Snapshot>),
+  /// Why the client's last command was not allowed
+  Error(ErrorMessage),
+}
+
+/// An error sent to a client
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ErrorMessage {
+  /// A short, stable name for the kind of error, see [`LabyrinthError::code`]
+  pub code: String,
+  /// A stable number for the kind of error, see [`LabyrinthError::number`]
+  pub number: u16,
+  /// What went wrong, to show to the player
+  pub message: String,
+}
+
+impl From<&LabyrinthError> for ErrorMessage {
+  fn from(error: &LabyrinthError) -> Self {
+    ErrorMessage {
+      code: error.code().to_string(),
+      number: error.number(),
+      message: error.to_string(),
+    }
+  }
+}
+
+/// Who plays a game served over TCP
+#[derive(Debug, Clone)]
+pub struct Server {
+  /// The players given to clients, in the order they connect
+  pub remote_players: Vec<Player>,
+  /// The bots playing the other players, which start once every client has connected
+  pub bots: Vec<(Player, BotKind)>,
+  /// Seed for the bots' choices
+  pub seed: u64,
+}
+
+/// Messages waiting to be sent to each client still connected
+type Outboxes = Mutex<HashMap<Player, Sender<ServerMessage>>>;
+
+/// A client's place in a game, through which it sends its commands
+pub(crate) struct Seat<'a> {
+  player: Player,
+  relay_tx: Sender<CommandRequest>,
+  outboxes: &'a Outboxes,
+}
+
+impl Seat<'_> {
+  /// Pass a message from the client on to be played, or tell the client why it is not a command
+  pub(crate) fn handle(&self, message: &str) {
+    match serde_json::from_str(message) {
+      // The relay sends the response to the client's outbox. It runs for as long as any seat is
+      // left, so it can only be gone if it has panicked, and then there is nobody to answer.
+      Ok(command) => {
+        let _ = self.relay_tx.send(CommandRequest {
+          sent_by: self.player,
+          command,
+          respond: channel().0,
+        });
+      }
+      Err(error) => {
+        let error = LabyrinthError::InvalidMessage {
+          reason: error.to_string(),
+        };
+        if let Some(outbox) = self.outboxes.lock().unwrap().get(&self.player) {
+          let _ = outbox.send(ServerMessage::Error(ErrorMessage::from(&error)));
+        }
+      }
+    }
+  }
+}
+
+/// Wait for a client to connect for each remote player, then play the game by passing everyone's
+/// commands on to the controller, which must be sending its events to `subscriptions`.
+/// Returns once every client has disconnected and every bot has stopped. Bots stop when the game is
+/// over, or when every client has disconnected before then, which also ends every subscriber's
+/// events.
+pub fn serve(
+  server: &Server,
+  listener: &TcpListener,
+  controller_tx: Sender<CommandRequest>,
+  subscriptions: &Subscriptions,
+) -> io::Result<()> {
+  let mut clients = Vec::new();
+  for player in &server.remote_players {
+    let (mut stream, _) = listener.accept()?;
+    write_message(&mut stream, &ServerMessage::Seated { player: *player })?;
+    clients.push((*player, stream));
+  }
+
+  // Everyone has a seat, so the game can start
+  play(
+    clients,
+    &server.bots,
+    server.seed,
+    controller_tx,
+    subscriptions,
+    talk_over_tcp,
+  );
+
+  Ok(())
+}
+
+/// Play a game between clients that have all connected and bots, passing everyone's commands on to
+/// the controller. Bots wait for their turns by listening to `subscriptions`.
+/// Each client is sent what it can see of the game to start with, then `talk` is run on its own
+/// thread to read its commands and send it the messages from its inbox, returning once the client
+/// has disconnected.
+pub(crate) fn play<C: Send>(
+  clients: Vec<(Player, C)>,
+  bots: &[(Player, BotKind)],
+  seed: u64,
+  controller_tx: Sender<CommandRequest>,
+  subscriptions: &Subscriptions,
+  talk: impl Fn(&Seat, Receiver<ServerMessage>, C) + Sync,
+) {
+  let outboxes: Outboxes = Mutex::new(HashMap::new());
+  let mut inboxes = Vec::new();
+  for (player, connection) in clients {
+    let (outbox, inbox) = channel();
+    let snapshot = send(&controller_tx, player, Command::NoOp);
+    outbox.send(response_message(snapshot)).unwrap();
+    outboxes.lock().unwrap().insert(player, outbox);
+    inboxes.push((player, inbox, connection));
+  }
+  // Subscribe before any client can leave and close the subscriptions
+  let bot_events: Vec<_> = bots
+    .iter()
+    .map(|(player, kind)| {
+      (
+        *player,
+        *kind,
+        subscriptions.subscribe(Viewer::Player(*player)),
+      )
+    })
+    .collect();
+
+  let (relay_tx, relay_rx) = channel();
+
+  thread::scope(|scope| {
+    let outboxes = &outboxes;
+    let talk = &talk;
+    scope.spawn(move || relay(relay_rx, &controller_tx, outboxes));
+
+    for (player, inbox, connection) in inboxes {
+      let seat = Seat {
+        player,
+        relay_tx: relay_tx.clone(),
+        outboxes,
+      };
+      scope.spawn(move || {
+        talk(&seat, inbox, connection);
+
+        let mut outboxes = outboxes.lock().unwrap();
+        outboxes.remove(&player);
+        // Nobody can finish the game now, so stop bots waiting for a turn that will never come
+        if outboxes.is_empty() {
+          subscriptions.close();
+        }
+      });
+    }
+
+    for (player, kind, events) in bot_events {
+      let relay_tx = relay_tx.clone();
+      let seed = seed.wrapping_add(player as u64);
+      scope.spawn(move || run_bot(kind.create(seed), player, relay_tx, events));
+    }
+    drop(relay_tx);
+  });
+}
+
+/// Pass every command on to the controller one at a time, sending clients the response to their
+/// own commands, and everyone else what they can now see whenever the game changes
+fn relay(
+  relay_rx: Receiver<CommandRequest>,
+  controller_tx: &Sender<CommandRequest>,
+  outboxes: &Outboxes,
+) {
+  for request in relay_rx {
+    // Clients leaving or being told about bad messages must not wait on the controller, so it is
+    // only talked to once the lock has been let go. Clients that leave in the meantime are still
+    // sent their messages, which are dropped along with their inboxes.
+    let outboxes: HashMap<Player, Sender<ServerMessage>> = outboxes.lock().unwrap().clone();
+
+    // Bots cannot finish the game on their own once every client has gone
+    if outboxes.is_empty() {
+      let _ = request.respond.send(Err(LabyrinthError::GameAbandoned));
+      continue;
+    }
+
+    let changes_game = !matches!(request.command, Command::NoOp);
+    let response = send(controller_tx, request.sent_by, request.command);
+
+    if changes_game && response.is_ok() {
+      for (player, outbox) in outboxes.iter() {
+        if *player != request.sent_by {
+          let snapshot = send(controller_tx, *player, Command::NoOp);
+          let _ = outbox.send(response_message(snapshot));
+        }
+      }
+    }
+
+    match outboxes.get(&request.sent_by) {
+      Some(outbox) => {
+        let _ = outbox.send(response_message(response));
+      }
+      None => {
+        let _ = request.respond.send(response);
+      }
+    }
+  }
+}
+
+/// Read a TCP client's commands one line at a time until it disconnects, sending it its messages
+/// on another thread
+fn talk_over_tcp(seat: &Seat, inbox: Receiver<ServerMessage>, stream: TcpStream) {
+  let mut writer = match stream.try_clone() {
+    Ok(writer) => writer,
+    Err(_) => return,
+  };
+  // Stops once the client has left and every message to it has been sent, or it cannot be sent to
+  thread::spawn(move || {
+    for message in inbox {
+      if write_message(&mut writer, &message).is_err() {
+        break;
+      }
+    }
+  });
+
+  for line in BufReader::new(stream).lines() {
+    match line {
+      Ok(line) if line.trim().is_empty() => {}
+      Ok(line) => seat.handle(&line),
+      Err(_) => break,
+    }
+  }
+}
+
+fn response_message(response: LabyrinthResult<Snapshot>) -> ServerMessage {
+  match response {
+    Ok(snapshot) => ServerMessage::Snapshot(Box::new(snapshot)),
+    Err(error) => ServerMessage::Error(ErrorMessage::from(&error)),
+  }
+}
+
+fn write_message(stream: &mut TcpStream, message: &ServerMessage) -> io::Result<()> {
+  let mut line = serde_json::to_string(message)?;
+  line.push('\n');
+  stream.write_all(line.as_bytes())
+}
+
+/// A connection to a server, for playing a served game from Rust
+pub struct Client {
+  reader: BufReader<TcpStream>,
+  writer: TcpStream,
+}
+
+impl Client {
+  pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+    let writer = TcpStream::connect(address)?;
+    let reader = BufReader::new(writer.try_clone()?);
+
+    Ok(Client { reader, writer })
+  }
+
+  /// Send a command, whose answer comes back from `receive` after any messages already on the way
+  pub fn send(&mut self, command: &Command) -> io::Result<()> {
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+    self.writer.write_all(line.as_bytes())
+  }
+
+  /// Wait for the next message from the server
+  pub fn receive(&mut self) -> io::Result<ServerMessage> {
+    let mut line = String::new();
+    if self.reader.read_line(&mut line)? == 0 {
+      return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(serde_json::from_str(&line)?)
+  }
+}
@@ -0,0 +1,325 @@
+use labyrinth::bot::{send, take_turn, Bot};
+use labyrinth::emoji::Emoji;
+use labyrinth::{
+  Command, CommandRequest, LabyrinthError, LabyrinthResult, Location, PlacedTile, Player, Rotation,
+  Snapshot, TurnPhase,
+};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::Sender;
+use strum::IntoEnumIterator;
+
+/// The largest board whose insertion arrows can all be given a letter
+pub const MAX_BOARD_SIZE: usize = 13;
+
+/// Each tile is drawn 6 characters wide with a 1 character gap between tiles
+const TILE_WIDTH: usize = 7;
+/// Each tile is drawn 4 lines high with a 1 line gap between rows of tiles
+const TILE_HEIGHT: usize = 5;
+
+/// What a player typed in at the prompt
+enum Input {
+  Command(Command),
+  /// Undo or redo, whoever made the move
+  History(Command),
+  Quit,
+}
+
+/// Play a hot-seat game in the terminal, with every human player taking turns at the same keyboard
+/// and bots taking their turns in between.
+/// Returns once the game is over or a player quits. Fails if the game can no longer be played, e.g.
+/// if the controller has stopped or a bot's turn was not allowed.
+pub fn play(
+  controller_tx: &Sender<CommandRequest>,
+  players: &HashSet<Player>,
+  bots: &mut HashMap<Player, Box<dyn Bot>>,
+) -> LabyrinthResult<()> {
+  let any_player = *players.iter().next().unwrap();
+  let mut snapshot = send(controller_tx, any_player, Command::NoOp)?;
+  let mut lines = io::stdin().lock().lines();
+
+  loop {
+    if let Some(standings) = &snapshot.standings {
+      println!("{}", render(&snapshot));
+      println!("Game over!");
+      for (place, standing) in standings.iter().enumerate() {
+        println!(
+          "{}. {} {:?} found {} cards",
+          place + 1,
+          standing.player,
+          standing.player,
+          standing.found
+        );
+      }
+      return Ok(());
+    }
+
+    let player = snapshot.next_player;
+    if let Some(bot) = bots.get_mut(&player) {
+      snapshot = take_turn(bot.as_mut(), player, controller_tx)?;
+      println!("{} {:?} has taken their turn", player, player);
+      continue;
+    }
+
+    snapshot = send(controller_tx, player, Command::NoOp)?;
+    println!("{}", render(&snapshot));
+
+    match snapshot.turn_phase {
+      TurnPhase::InsertTile => print!(
+        "{} {:?}: insert the tile by choosing an arrow and a rotation, e.g. B2 \
+        (u to undo, r to redo, q to quit): ",
+        player, player
+      ),
+      _ => print!(
+        "{} {:?}: move by choosing a column and row, e.g. 3 4, or press enter to stay \
+        (u to undo, r to redo, q to quit): ",
+        player, player
+      ),
+    }
+    io::stdout().flush().unwrap();
+
+    let line = match lines.next() {
+      Some(Ok(line)) => line,
+      _ => return Ok(()),
+    };
+
+    let result = match parse_input(&line, &snapshot, player) {
+      Ok(Input::Quit) => return Ok(()),
+      Ok(Input::History(command)) => undo_or_redo(controller_tx, player, command),
+      Ok(Input::Command(command)) => send(controller_tx, player, command),
+      Err(message) => {
+        println!("{}", message);
+        continue;
+      }
+    };
+
+    match result {
+      Ok(new_snapshot) => snapshot = new_snapshot,
+      Err(error) => println!("{}", error),
+    }
+  }
+}
+
+/// The arrows around the edge of a board `size` tiles across that the spare tile can be pushed in
+/// from, in the order they are lettered: top, right and bottom from left to right or top to bottom,
+/// then left
+fn insertion_arrows(size: usize) -> Vec<(char, Location)> {
+  let last = size - 1;
+  let odd = || (1..last).step_by(2);
+
+  let locations = odd()
+    .map(|x| Location(x, 0))
+    .chain(odd().map(|y| Location(last, y)))
+    .chain(odd().map(|x| Location(x, last)))
+    .chain(odd().map(|y| Location(0, y)));
+
+  ('A'..='Z').zip(locations).collect()
+}
+
+/// Undo or redo the last move, whoever made it, as everyone shares the same keyboard
+fn undo_or_redo(
+  controller_tx: &Sender<CommandRequest>,
+  player: Player,
+  command: Command,
+) -> LabyrinthResult<Snapshot> {
+  match send(controller_tx, player, command.clone()) {
+    Err(LabyrinthError::NotYourMove { expected, .. }) => send(controller_tx, expected, command),
+    result => result,
+  }
+}
+
+fn parse_input(line: &str, snapshot: &Snapshot, player: Player) -> Result<Input, String> {
+  let line = line.trim().to_uppercase();
+
+  match line.as_str() {
+    "Q" => return Ok(Input::Quit),
+    "U" => return Ok(Input::History(Command::Undo)),
+    "R" => return Ok(Input::History(Command::Redo)),
+    _ => {}
+  }
+
+  if snapshot.turn_phase == TurnPhase::InsertTile {
+    let arrows = insertion_arrows(snapshot.board_size);
+    let mut chars = line.chars();
+    let location = chars
+      .next()
+      .and_then(|letter| {
+        arrows
+          .iter()
+          .find(|(arrow, _)| *arrow == letter)
+          .map(|(_, location)| *location)
+      })
+      .ok_or_else(|| format!("Choose an arrow from A to {}", arrows.last().unwrap().0))?;
+    let rotation = chars
+      .as_str()
+      .trim()
+      .parse::<usize>()
+      .ok()
+      .and_then(|i| Rotation::iter().nth(i))
+      .ok_or("Choose a rotation from 0 to 3")?;
+
+    Ok(Input::Command(Command::InsertTile(location, rotation)))
+  } else if line.is_empty() {
+    let here = snapshot
+      .board
+      .iter()
+      .find(|(_, tile)| tile.players.contains(&player))
+      .map(|(location, _)| *location)
+      .ok_or("You are not on the board")?;
+
+    Ok(Input::Command(Command::MovePlayer(player, here)))
+  } else {
+    let numbers: Vec<usize> = line
+      .split(|c: char| c == ',' || c.is_whitespace())
+      .filter(|part| !part.is_empty())
+      .map(|part| part.parse::<usize>())
+      .collect::<Result<_, _>>()
+      .map_err(|_| "Choose a column and row, e.g. 3 4")?;
+
+    match numbers[..] {
+      [x, y] => Ok(Input::Command(Command::MovePlayer(player, Location(x, y)))),
+      _ => Err("Choose a column and row, e.g. 3 4".to_string()),
+    }
+  }
+}
+
+/// Draw the board with its insertion arrows, the spare tile and everyone's cards
+pub fn render(snapshot: &Snapshot) -> String {
+  [
+    render_board(snapshot),
+    render_spare(snapshot),
+    render_cards(snapshot),
+  ]
+  .join("\n\n")
+}
+
+fn arrow_label(snapshot: &Snapshot, location: Location) -> Option<char> {
+  insertion_arrows(snapshot.board_size)
+    .into_iter()
+    .find(|(_, arrow_at)| *arrow_at == location)
+    .map(|(letter, _)| {
+      if snapshot.forbidden_insertion == Some(location) {
+        'x'
+      } else {
+        letter
+      }
+    })
+}
+
+fn render_board(snapshot: &Snapshot) -> String {
+  let board_str = format!("{:?}", snapshot.to_board());
+  let margin = " ".repeat(5);
+
+  // Column numbers, with the top arrows underneath
+  let mut column_numbers = margin.clone();
+  let mut top_arrows = margin.clone();
+  let mut bottom_arrows = margin.clone();
+  let last = snapshot.board_size - 1;
+  for x in 0..snapshot.board_size {
+    column_numbers.push_str(&format!("{:^6} ", x));
+    top_arrows.push_str(&match arrow_label(snapshot, Location(x, 0)) {
+      Some(letter) => format!("{:^6} ", format!("{}↓", letter)),
+      None => " ".repeat(TILE_WIDTH),
+    });
+    bottom_arrows.push_str(&match arrow_label(snapshot, Location(x, last)) {
+      Some(letter) => format!("{:^6} ", format!("{}↑", letter)),
+      None => " ".repeat(TILE_WIDTH),
+    });
+  }
+
+  // Row numbers and the left and right arrows beside the middle of each row
+  let rows: Vec<String> = board_str
+    .lines()
+    .enumerate()
+    .map(|(i, line)| {
+      let y = i / TILE_HEIGHT;
+      if i % TILE_HEIGHT != 1 {
+        return format!("{}{}", margin, line);
+      }
+
+      let left = arrow_label(snapshot, Location(0, y))
+        .map(|letter| format!("{}→", letter))
+        .unwrap_or_default();
+      let right = arrow_label(snapshot, Location(last, y))
+        .map(|letter| format!("←{}", letter))
+        .unwrap_or_default();
+
+      format!("{:<2} {:>2}{} {}", y, left, line, right)
+    })
+    .collect();
+
+  [column_numbers, top_arrows, rows.join("\n"), bottom_arrows].join("\n")
+}
+
+fn render_spare(snapshot: &Snapshot) -> String {
+  let rotated: Vec<Vec<String>> = Rotation::iter()
+    .map(|rotation| {
+      let placed_tile = PlacedTile {
+        tile: snapshot.spare_tile,
+        rotation,
+        players: HashSet::new(),
+      };
+      format!("{:?}", placed_tile)
+        .lines()
+        .map(String::from)
+        .collect()
+    })
+    .collect();
+
+  let mut lines = vec!["Spare tile rotations:".to_string()];
+  lines.push(
+    (0..rotated.len())
+      .map(|i| format!("{:^6}", i))
+      .collect::<Vec<_>>()
+      .join("   "),
+  );
+  for line in 0..rotated[0].len() {
+    lines.push(
+      rotated
+        .iter()
+        .map(|tile_lines| tile_lines[line].clone())
+        .collect::<Vec<_>>()
+        .join("   "),
+    );
+  }
+
+  lines.join("\n")
+}
+
+fn render_cards(snapshot: &Snapshot) -> String {
+  let mut players: Vec<&Player> = snapshot.players.keys().collect();
+  players.sort_by_key(|player| **player as u8);
+
+  let mut lines: Vec<String> = players
+    .into_iter()
+    .map(|player| {
+      let cards = &snapshot.players[player];
+      let found: String = cards.found.iter().map(|item| item.emoji()).collect();
+      format!(
+        "{} {:?}: {} cards left, found {}",
+        player, player, cards.num_remaining, found
+      )
+    })
+    .collect();
+
+  if snapshot.draw_pile > 0 {
+    lines.push(format!("{} cards left to draw", snapshot.draw_pile));
+  }
+
+  if let Some(item) = snapshot.looking_for {
+    lines.push(format!(
+      "{} {:?} is looking for {} {}",
+      snapshot.next_player,
+      snapshot.next_player,
+      item.emoji(),
+      item
+    ));
+  } else if snapshot.players[&snapshot.next_player].num_remaining == 0 {
+    lines.push(format!(
+      "{} {:?} has found every card and must return to their start",
+      snapshot.next_player, snapshot.next_player
+    ));
+  }
+
+  lines.join("\n")
+}
@@ -0,0 +1,502 @@
+use crate::board::Player;
+use crate::bot::{Bot, BotKind};
+use crate::controller::{apply_command, Command, History, Snapshot};
+use crate::errors::{LabyrinthError, LabyrinthResult};
+use crate::model::{next_player, GameConfig, Model, TurnPhase};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+
+/// A run of games between bots, to compare how strong they are and check the rules hold up
+#[derive(Debug, Clone)]
+pub struct Tournament {
+  /// The bots taking part, one for each player. In the first game the first seat plays the lowest
+  /// numbered player, and so on. Each game after that the bots move round one seat, so every bot
+  /// gets to play from every start position.
+  pub seats: Vec<BotKind>,
+  /// The setup for the first game. Each game after that uses the next seed.
+  pub config: GameConfig,
+  pub games: u32,
+  /// Games nobody has won after this many turns are abandoned
+  pub max_turns: u32,
+  /// How many games to play at the same time
+  pub threads: usize,
+}
+
+/// How one game of a tournament ended
+#[derive(Debug, Copy, Clone)]
+pub struct GameResult {
+  pub seed: u64,
+  /// The player and seat that won, or None if the game was abandoned
+  pub winner: Option<(Player, usize)>,
+  pub turns: u32,
+}
+
+/// How often a seat or player won, and how long the games they won took
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct WinRecord {
+  pub name: String,
+  pub games: u32,
+  pub wins: u32,
+  /// Games nobody had won when they were abandoned, which count as games but never as wins
+  pub abandoned: u32,
+  pub win_rate: f64,
+  /// The average number of turns in the games won, leaving out abandoned games
+  pub average_turns_to_win: f64,
+}
+
+impl WinRecord {
+  fn new<'a>(
+    name: String,
+    games: u32,
+    abandoned: u32,
+    won: impl Iterator<Item = &'a GameResult>,
+  ) -> Self {
+    let turns: Vec<u32> = won.map(|result| result.turns).collect();
+    let wins = turns.len() as u32;
+
+    WinRecord {
+      name,
+      games,
+      wins,
+      abandoned,
+      win_rate: if games > 0 {
+        wins as f64 / games as f64
+      } else {
+        0.0
+      },
+      average_turns_to_win: if wins > 0 {
+        turns.iter().sum::<u32>() as f64 / wins as f64
+      } else {
+        0.0
+      },
+    }
+  }
+}
+
+/// The results of a tournament
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone)]
+pub struct TournamentReport {
+  /// Every game that was won, and how long they took, and how many games were abandoned
+  pub finished: WinRecord,
+  /// How each seat did, whichever player it was playing
+  pub seats: Vec<WinRecord>,
+  /// How each player did, whichever bot was playing it, which shows the advantage of each start
+  /// position
+  pub players: Vec<WinRecord>,
+}
+
+impl TournamentReport {
+  fn new(tournament: &Tournament, results: &[GameResult]) -> Self {
+    let games = results.len() as u32;
+    let players = seat_order(&tournament.config);
+    let abandoned = results
+      .iter()
+      .filter(|result| result.winner.is_none())
+      .count() as u32;
+
+    TournamentReport {
+      finished: WinRecord::new(
+        "all".to_string(),
+        games,
+        abandoned,
+        results.iter().filter(|result| result.winner.is_some()),
+      ),
+      seats: tournament
+        .seats
+        .iter()
+        .enumerate()
+        .map(|(seat, bot)| {
+          WinRecord::new(
+            format!("{} {}", seat + 1, bot),
+            games,
+            abandoned,
+            results
+              .iter()
+              .filter(|result| matches!(result.winner, Some((_, s)) if s == seat)),
+          )
+        })
+        .collect(),
+      players: players
+        .iter()
+        .map(|player| {
+          WinRecord::new(
+            format!("{:?}", player),
+            games,
+            abandoned,
+            results
+              .iter()
+              .filter(|result| matches!(result.winner, Some((p, _)) if p == *player)),
+          )
+        })
+        .collect(),
+    }
+  }
+
+  /// The report as CSV, with a row for all games, then each seat, then each player
+  pub fn to_csv(&self) -> String {
+    let rows = [("all", &self.finished)]
+      .into_iter()
+      .chain(self.seats.iter().map(|record| ("seat", record)))
+      .chain(self.players.iter().map(|record| ("player", record)))
+      .map(|(kind, record)| {
+        format!(
+          "{},{},{},{},{},{:.3},{:.1}\n",
+          kind,
+          record.name,
+          record.games,
+          record.wins,
+          record.abandoned,
+          record.win_rate,
+          record.average_turns_to_win
+        )
+      });
+
+    std::iter::once("kind,name,games,wins,abandoned,win_rate,average_turns_to_win\n".to_string())
+      .chain(rows)
+      .collect()
+  }
+
+  /// The report as JSON
+  #[cfg(feature = "serde")]
+  pub fn to_json(&self) -> LabyrinthResult<String> {
+    serde_json::to_string_pretty(self).map_err(|error| LabyrinthError::InvalidSave {
+      reason: error.to_string(),
+    })
+  }
+}
+
+impl fmt::Display for TournamentReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "{} of {} games won, taking {:.1} turns on average to win, and {} abandoned",
+      self.finished.wins,
+      self.finished.games,
+      self.finished.average_turns_to_win,
+      self.finished.abandoned
+    )?;
+
+    for (heading, records) in [("Seat", &self.seats), ("Player", &self.players)] {
+      writeln!(f)?;
+      writeln!(
+        f,
+        "{:<12} {:>6} {:>8} {:>12}",
+        heading, "wins", "win rate", "turns to win"
+      )?;
+      for record in records {
+        writeln!(
+          f,
+          "{:<12} {:>6} {:>7.1}% {:>12.1}",
+          record.name,
+          record.wins,
+          record.win_rate * 100.0,
+          record.average_turns_to_win
+        )?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Play every game of the tournament, spread over several threads.
+/// Stops at the first game that panics, breaks the rules or has a bot send an illegal command,
+/// returning an error with the seed of the game so it can be played again.
+pub fn run_tournament(tournament: &Tournament) -> LabyrinthResult<TournamentReport> {
+  run_games(tournament, &|seat, seed| {
+    tournament.seats[seat].create(seed)
+  })
+}
+
+/// Creates the bot for a seat from its seed
+type CreateBot<'a> = dyn Fn(usize, u64) -> Box<dyn Bot> + Sync + 'a;
+
+/// Play every game of the tournament like `run_tournament`, with bots made by `create_bot`
+fn run_games(tournament: &Tournament, create_bot: &CreateBot) -> LabyrinthResult<TournamentReport> {
+  tournament.config.validate()?;
+  if tournament.seats.len() != tournament.config.players.len() {
+    return Err(LabyrinthError::InvalidConfig {
+      reason: format!(
+        "{} bots cannot play a game for {} players",
+        tournament.seats.len(),
+        tournament.config.players.len()
+      ),
+    });
+  }
+
+  let next_game = AtomicU32::new(0);
+  let failed = AtomicBool::new(false);
+  let (result_tx, result_rx) = channel();
+
+  thread::scope(|scope| {
+    for _ in 0..tournament.threads.max(1) {
+      let result_tx = result_tx.clone();
+      let next_game = &next_game;
+      let failed = &failed;
+
+      scope.spawn(move || loop {
+        let game = next_game.fetch_add(1, Ordering::SeqCst);
+        if game >= tournament.games || failed.load(Ordering::SeqCst) {
+          return;
+        }
+
+        let result = play_game(tournament, game, create_bot);
+        if result.is_err() {
+          failed.store(true, Ordering::SeqCst);
+        }
+        result_tx.send(result).unwrap();
+      });
+    }
+  });
+  drop(result_tx);
+
+  let mut results = result_rx.into_iter().collect::<LabyrinthResult<Vec<_>>>()?;
+  results.sort_by_key(|result| result.seed);
+
+  Ok(TournamentReport::new(tournament, &results))
+}
+
+/// The players in the order seats are given to them
+fn seat_order(config: &GameConfig) -> Vec<Player> {
+  let mut players: Vec<Player> = config.players.iter().copied().collect();
+  players.sort_by_key(|player| *player as u8);
+  players
+}
+
+/// The seat playing each player in a game. Seats move round one player each game.
+fn seats_for_game(config: &GameConfig, game: u32) -> HashMap<Player, usize> {
+  let players = seat_order(config);
+  players
+    .iter()
+    .enumerate()
+    .map(|(i, player)| (*player, (i + game as usize) % players.len()))
+    .collect()
+}
+
+/// Play one game of the tournament, turning any panic into an error
+fn play_game(
+  tournament: &Tournament,
+  game: u32,
+  create_bot: &CreateBot,
+) -> LabyrinthResult<GameResult> {
+  let seed = tournament.config.seed.wrapping_add(game as u64);
+  let config = GameConfig {
+    seed,
+    ..tournament.config.clone()
+  };
+  let seats = seats_for_game(&config, game);
+
+  let played = panic::catch_unwind(AssertUnwindSafe(|| {
+    let mut bots: HashMap<Player, Box<dyn Bot>> = seats
+      .iter()
+      .map(|(player, seat)| {
+        let bot = create_bot(*seat, seed.wrapping_add(*player as u64));
+        (*player, bot)
+      })
+      .collect();
+
+    play(&config, &mut bots, tournament.max_turns)
+  }));
+
+  let reason = match played {
+    Ok(Ok((winner, turns))) => {
+      return Ok(GameResult {
+        seed,
+        winner: winner.map(|winner| (winner, seats[&winner])),
+        turns,
+      })
+    }
+    Ok(Err(error)) => error.to_string(),
+    Err(panic) => panic_message(panic),
+  };
+
+  Err(LabyrinthError::GameFailed { seed, reason })
+}
+
+/// Play a game between bots, checking the rules still hold after every command. Commands go
+/// straight to `apply_command`, which makes the same checks as the controller, rather than through
+/// the controller's channels, so that the game can be checked between commands and abandoned after
+/// `max_turns`. Returns the winner, if anyone won, and how many turns were played.
+fn play(
+  config: &GameConfig,
+  bots: &mut HashMap<Player, Box<dyn Bot>>,
+  max_turns: u32,
+) -> LabyrinthResult<(Option<Player>, u32)> {
+  let mut model = Model::new(config)?;
+  let mut history = History::default();
+  model.check_invariants()?;
+  let num_cards = count_cards(&model);
+
+  let mut turns = 0;
+  while !model.is_game_over() && turns < max_turns {
+    let player = model.current_player;
+    let command = bots
+      .get_mut(&player)
+      .unwrap()
+      .choose(&Snapshot::for_player(&model, player));
+
+    apply_command(&mut model, &mut history, player, &command)?;
+
+    model.check_invariants()?;
+    if count_cards(&model) != num_cards {
+      return Err(LabyrinthError::BrokenInvariant {
+        reason: format!(
+          "{} cards were dealt but there are now {}",
+          num_cards,
+          count_cards(&model)
+        ),
+      });
+    }
+
+    if let Command::MovePlayer(_, _) = command {
+      turns += 1;
+
+      let expected = next_player(&config.players, player)?;
+      if !model.is_game_over() && model.current_player != expected {
+        return Err(LabyrinthError::BrokenInvariant {
+          reason: format!(
+            "{:?} should have played after {:?}, not {:?}",
+            expected, player, model.current_player
+          ),
+        });
+      }
+    }
+  }
+
+  let winner = match model.turn_phase {
+    TurnPhase::GameOver { winner } => Some(winner),
+    _ => None,
+  };
+
+  Ok((winner, turns))
+}
+
+/// How many cards are in the game, whether found, still to find or still to draw
+fn count_cards(model: &Model) -> usize {
+  let in_hands: usize = model
+    .players
+    .values()
+    .map(|cards| cards.found_cards.len() + cards.remaining())
+    .sum();
+
+  in_hands + model.draw_pile.len()
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+  panic
+    .downcast_ref::<&str>()
+    .map(|message| message.to_string())
+    .or_else(|| panic.downcast_ref::<String>().cloned())
+    .unwrap_or_else(|| "panicked".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::board::Location;
+  use crate::model::DealingPolicy;
+  use std::collections::HashSet;
+
+  fn tournament(players: &[Player], games: u32, max_turns: u32) -> Tournament {
+    Tournament {
+      seats: vec![BotKind::Random; players.len()],
+      config: GameConfig {
+        seed: 10,
+        players: players.iter().copied().collect(),
+        starting_player: Player::Player1,
+        allow_undo: false,
+        dealing: DealingPolicy::FixedHandSize(1),
+        board_size: 5,
+        layout: None,
+      },
+      games,
+      max_turns,
+      threads: 2,
+    }
+  }
+
+  /// Takes one turn, then panics or sends a command that is never allowed
+  struct FailingBot {
+    panics: bool,
+    turns: u32,
+  }
+
+  impl Bot for FailingBot {
+    fn choose(&mut self, snapshot: &Snapshot) -> Command {
+      self.turns += 1;
+      if self.turns == 1 {
+        return BotKind::Random.create(0).choose(snapshot);
+      }
+      if self.panics {
+        panic!("the bot gave up");
+      }
+      Command::MovePlayer(snapshot.next_player, Location(99, 99))
+    }
+  }
+
+  #[test]
+  fn every_seat_plays_every_player_once_as_the_seats_move_round() {
+    let tournament = tournament(&[Player::Player1, Player::Player2, Player::Player4], 3, 1);
+    let mut played = HashSet::new();
+
+    for game in 0..3 {
+      let seats = seats_for_game(&tournament.config, game);
+      assert_eq!(seats[&Player::Player1], game as usize);
+      assert_eq!(seats.values().collect::<HashSet<_>>().len(), 3);
+      played.extend(seats);
+    }
+    assert_eq!(played.len(), 9);
+  }
+
+  #[test]
+  fn games_nobody_wins_in_time_are_abandoned() {
+    let report = run_tournament(&tournament(&[Player::Player1, Player::Player2], 4, 0)).unwrap();
+    assert_eq!(report.finished.games, 4);
+    assert_eq!(report.finished.wins, 0);
+    assert_eq!(report.finished.abandoned, 4);
+    assert_eq!(report.finished.win_rate, 0.0);
+
+    let report = run_tournament(&tournament(&[Player::Player1, Player::Player2], 8, 4)).unwrap();
+    assert_eq!(report.finished.wins + report.finished.abandoned, 8);
+    for records in [&report.seats, &report.players] {
+      let wins: u32 = records.iter().map(|record| record.wins).sum();
+      assert_eq!(wins, report.finished.wins);
+      assert!(records
+        .iter()
+        .all(|record| record.abandoned == report.finished.abandoned));
+    }
+  }
+
+  #[test]
+  fn a_failing_bot_fails_the_tournament_with_the_seed_of_its_game() {
+    let tournament = tournament(&[Player::Player1, Player::Player2], 4, 100);
+    let seeds = tournament.config.seed..tournament.config.seed + 4;
+
+    for panics in [false, true] {
+      let result = run_games(&tournament, &|seat, seed| {
+        if seat == 1 {
+          Box::new(FailingBot { panics, turns: 0 })
+        } else {
+          BotKind::Random.create(seed)
+        }
+      });
+
+      match result {
+        Err(LabyrinthError::GameFailed { seed, reason }) => {
+          assert!(seeds.contains(&seed), "seed {}", seed);
+          if panics {
+            assert_eq!(reason, "the bot gave up");
+          }
+        }
+        _ => panic!("the tournament should have failed"),
+      }
+    }
+  }
+}
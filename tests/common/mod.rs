@@ -0,0 +1,105 @@
+//! Games and helpers shared by the integration tests
+
+// Each test file uses only some of these
+#![allow(dead_code)]
+
+#[cfg(feature = "serde")]
+use labyrinth::server::ServerMessage;
+use labyrinth::{
+  run_controller_with_subscriptions, Command, CommandRequest, DealingPolicy, GameConfig, GameLog,
+  Model, Player, Snapshot, Subscriptions,
+};
+use std::collections::HashSet;
+#[cfg(feature = "serde")]
+use std::io;
+#[cfg(feature = "serde")]
+use std::net::{SocketAddr, TcpListener};
+use std::sync::mpsc::{channel, Sender};
+use std::thread::{self, JoinHandle};
+
+/// A game between players 1 and 2, on the standard board with every card dealt
+pub fn config(seed: u64) -> GameConfig {
+  GameConfig {
+    seed,
+    players: HashSet::from([Player::Player1, Player::Player2]),
+    starting_player: Player::Player1,
+    allow_undo: true,
+    dealing: DealingPolicy::EqualHands,
+    board_size: 7,
+    layout: None,
+  }
+}
+
+/// Run the controller on its own thread, sending events to the subscriptions returned.
+/// The game log is returned once every sender has been dropped.
+pub fn start_controller(
+  model: Model,
+) -> (Sender<CommandRequest>, Subscriptions, JoinHandle<GameLog>) {
+  let (controller_tx, controller_rx) = channel();
+  let subscriptions = Subscriptions::default();
+  let game = subscriptions.clone();
+  let controller =
+    thread::spawn(move || run_controller_with_subscriptions(model, controller_rx, &game));
+
+  (controller_tx, subscriptions, controller)
+}
+
+/// Serve the game from `config(1)` on a free port with `serve`, which is given the listener, the
+/// controller and its subscriptions. The game log is returned once `serve` has returned.
+#[cfg(feature = "serde")]
+pub fn serve_game(
+  serve: impl FnOnce(&TcpListener, Sender<CommandRequest>, &Subscriptions) -> io::Result<()>
+    + Send
+    + 'static,
+) -> (SocketAddr, JoinHandle<GameLog>) {
+  let model = Model::new(&config(1)).unwrap();
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let address = listener.local_addr().unwrap();
+
+  let handle = thread::spawn(move || {
+    let (controller_tx, subscriptions, controller) = start_controller(model);
+    serve(&listener, controller_tx, &subscriptions).unwrap();
+    controller.join().unwrap()
+  });
+
+  (address, handle)
+}
+
+/// A client of a served game, however it is connected
+#[cfg(feature = "serde")]
+pub trait Connected {
+  /// Wait for the next message from the server
+  fn next_message(&mut self) -> ServerMessage;
+}
+
+#[cfg(feature = "serde")]
+pub fn seated(client: &mut impl Connected) -> Player {
+  match client.next_message() {
+    ServerMessage::Seated { player } => player,
+    _ => panic!("expected to be seated"),
+  }
+}
+
+#[cfg(feature = "serde")]
+pub fn snapshot(client: &mut impl Connected) -> Snapshot {
+  match client.next_message() {
+    ServerMessage::Snapshot(snapshot) => *snapshot,
+    ServerMessage::Error(error) => panic!("expected a snapshot, got {:?}", error),
+    _ => panic!("expected a snapshot"),
+  }
+}
+
+#[cfg(feature = "serde")]
+pub fn error_code(client: &mut impl Connected) -> String {
+  match client.next_message() {
+    ServerMessage::Error(error) => error.code,
+    _ => panic!("expected an error"),
+  }
+}
+
+/// Insert the spare tile anywhere it is allowed
+pub fn any_insertion(snapshot: &Snapshot) -> Command {
+  let (location, rotation) = snapshot.to_board().legal_insertions()[0];
+  Command::InsertTile(location, rotation)
+}
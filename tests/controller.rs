@@ -0,0 +1,372 @@
+mod common;
+
+use common::start_controller;
+use labyrinth::bot::{send, BotKind};
+use labyrinth::controller::{apply_command, History};
+use labyrinth::mcts::SearchBudget;
+use labyrinth::packed::PackedBoard;
+use labyrinth::{
+  Command, CommandRequest, DealingPolicy, GameConfig, Item, LabyrinthError, Location, Model,
+  Player, Rotation, Snapshot, TileMarking, Turn, TurnPhase, Viewer,
+};
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+
+/// A short game, with one card each on the smallest board
+fn config(seed: u64) -> GameConfig {
+  GameConfig {
+    dealing: DealingPolicy::FixedHandSize(1),
+    board_size: 5,
+    ..common::config(seed)
+  }
+}
+
+#[test]
+fn the_winning_move_cannot_be_undone() {
+  let mut model = Model::new(&config(1)).unwrap();
+  let mut history = History::default();
+  let mut bots = [BotKind::Greedy.create(1), BotKind::Greedy.create(2)];
+
+  while !model.is_game_over() {
+    let player = model.current_player;
+    let command = bots[player as usize].choose(&Snapshot::for_player(&model, player));
+    apply_command(&mut model, &mut history, player, &command).unwrap();
+  }
+  let winner = match model.turn_phase {
+    TurnPhase::GameOver { winner } => winner,
+    _ => unreachable!(),
+  };
+
+  for command in [Command::Undo, Command::Redo] {
+    assert!(matches!(
+      apply_command(&mut model, &mut history, winner, &command),
+      Err(LabyrinthError::GameOver { winner: won }) if won == winner
+    ));
+  }
+  assert_eq!(model.turn_phase, TurnPhase::GameOver { winner });
+}
+
+#[test]
+fn the_game_goes_on_when_a_sender_stops_waiting_for_its_response() {
+  let (controller_tx, _, controller) = start_controller(Model::new(&config(1)).unwrap());
+
+  for command in [Command::NoOp, Command::Undo] {
+    let (respond, _) = channel();
+    controller_tx
+      .send(CommandRequest {
+        sent_by: Player::Player1,
+        command,
+        respond,
+      })
+      .unwrap();
+  }
+  assert!(send(&controller_tx, Player::Player2, Command::NoOp).is_ok());
+
+  drop(controller_tx);
+  controller.join().unwrap();
+}
+
+#[test]
+fn an_mcts_bot_with_no_iterations_still_takes_its_turn() {
+  let mut model = Model::new(&config(2)).unwrap();
+  let mut history = History::default();
+  let mut bot = BotKind::Mcts(SearchBudget::Iterations(0)).create(2);
+
+  // Insert, then move
+  for _ in 0..2 {
+    let command = bot.choose(&Snapshot::for_player(&model, Player::Player1));
+    apply_command(&mut model, &mut history, Player::Player1, &command).unwrap();
+  }
+  assert_eq!(model.current_player, Player::Player2);
+}
+
+/// Insert the spare tile at a location, with the first rotation that fits there
+fn insert_at(snapshot: &Snapshot, location: Location) -> Command {
+  let (_, rotation) = *snapshot
+    .to_board()
+    .legal_insertions()
+    .iter()
+    .find(|(at, _)| *at == location)
+    .unwrap();
+  Command::InsertTile(location, rotation)
+}
+
+/// Move the player to where they already are, ending their turn
+fn stay_put(snapshot: &Snapshot, player: Player) -> Command {
+  let at = snapshot.to_board().player_location(&player).unwrap();
+  Command::MovePlayer(player, at)
+}
+
+#[test]
+fn the_last_insertion_cannot_be_pushed_straight_back() {
+  let (controller_tx, _, controller) = start_controller(Model::new(&config(3)).unwrap());
+  let snapshot = send(&controller_tx, Player::Player1, Command::NoOp).unwrap();
+  assert_eq!(snapshot.forbidden_insertion, None);
+
+  // Inserting at the top of a column pushes out the tile at the bottom
+  let command = insert_at(&snapshot, Location(1, 0));
+  let snapshot = send(&controller_tx, Player::Player1, command).unwrap();
+  assert_eq!(snapshot.forbidden_insertion, Some(Location(1, 4)));
+  let snapshot = send(
+    &controller_tx,
+    Player::Player1,
+    stay_put(&snapshot, Player::Player1),
+  )
+  .unwrap();
+
+  let reverse = Command::InsertTile(Location(1, 4), Rotation::Zero);
+  assert!(matches!(
+    send(&controller_tx, Player::Player2, reverse),
+    Err(LabyrinthError::ReversedInsertion { location }) if location == Location(1, 4)
+  ));
+
+  // Any other insertion frees the slot again
+  let command = insert_at(&snapshot, Location(3, 0));
+  let snapshot = send(&controller_tx, Player::Player2, command).unwrap();
+  assert_eq!(snapshot.forbidden_insertion, Some(Location(3, 4)));
+  let snapshot = send(
+    &controller_tx,
+    Player::Player2,
+    stay_put(&snapshot, Player::Player2),
+  )
+  .unwrap();
+  let command = insert_at(&snapshot, Location(1, 4));
+  assert!(send(&controller_tx, Player::Player1, command).is_ok());
+
+  drop(controller_tx);
+  controller.join().unwrap();
+}
+
+#[test]
+fn every_legal_turn_is_accepted_by_the_controller() {
+  let mut model = Model::new(&config(4)).unwrap();
+  let mut history = History::default();
+  let mut bots = [BotKind::Greedy.create(1), BotKind::Greedy.create(2)];
+
+  for _ in 0..6 {
+    let player = model.current_player;
+    let turns: Vec<Turn> = model.legal_turns().collect();
+    assert!(!turns.is_empty());
+    assert_eq!(turns.iter().collect::<HashSet<_>>().len(), turns.len());
+
+    for turn in &turns {
+      let mut played = model.clone();
+      let mut history = History::default();
+      for command in [
+        Command::InsertTile(turn.insert_at, turn.rotation),
+        Command::MovePlayer(player, turn.move_to),
+      ] {
+        apply_command(&mut played, &mut history, player, &command).unwrap();
+      }
+    }
+
+    // Play on with a whole turn, insert then move
+    for _ in 0..2 {
+      let command = bots[player as usize].choose(&Snapshot::for_player(&model, player));
+      apply_command(&mut model, &mut history, player, &command).unwrap();
+    }
+  }
+}
+
+/// Check that two games are in the same state: the same tiles, rotations, pawns and spare tile on
+/// the board, and the same cards in every hand and in the draw pile
+fn assert_same_game(model: &Model, expected: &Model) {
+  assert_eq!(
+    PackedBoard::try_from(&model.board).unwrap(),
+    PackedBoard::try_from(&expected.board).unwrap()
+  );
+  assert_eq!(model.current_player, expected.current_player);
+  assert_eq!(model.turn_phase, expected.turn_phase);
+  assert_eq!(model.draw_pile, expected.draw_pile);
+  for (player, expected_cards) in &expected.players {
+    let cards = &model.players[player];
+    assert_eq!(cards.current_card, expected_cards.current_card);
+    assert_eq!(cards.hidden_cards, expected_cards.hidden_cards);
+    assert_eq!(cards.found_cards, expected_cards.found_cards);
+  }
+}
+
+#[test]
+fn undoing_an_insertion_puts_back_the_tiles_and_a_pawn_carried_across_the_edge() {
+  let mut model = Model::new(&config(5)).unwrap();
+  let mut history = History::default();
+
+  // Inserting at the top of the column pushes the pawn at the bottom round to the top
+  let insert_at = Location(1, 0);
+  model
+    .board
+    .place_player(&Player::Player1, &Location(1, 4))
+    .unwrap();
+  let (_, rotation) = *model
+    .board
+    .legal_insertions()
+    .iter()
+    .find(|(location, _)| *location == insert_at)
+    .unwrap();
+  let before = model.clone();
+
+  let insert = Command::InsertTile(insert_at, rotation);
+  apply_command(&mut model, &mut history, Player::Player1, &insert).unwrap();
+  assert_eq!(
+    model.board.player_location(&Player::Player1),
+    Some(insert_at)
+  );
+  let after = model.clone();
+
+  apply_command(&mut model, &mut history, Player::Player1, &Command::Undo).unwrap();
+  assert_same_game(&model, &before);
+
+  apply_command(&mut model, &mut history, Player::Player1, &Command::Redo).unwrap();
+  assert_same_game(&model, &after);
+}
+
+#[test]
+fn undoing_a_move_puts_back_the_card_it_found() {
+  for dealing in [DealingPolicy::EqualHands, DealingPolicy::SharedPile] {
+    let mut model = Model::new(&GameConfig {
+      dealing,
+      ..config(6)
+    })
+    .unwrap();
+    let mut history = History::default();
+
+    let (insert_at, rotation) = model.board.legal_insertions()[0];
+    let insert = Command::InsertTile(insert_at, rotation);
+    apply_command(&mut model, &mut history, Player::Player1, &insert).unwrap();
+
+    // Stand on the item being looked for, so that staying put finds it
+    let item = model.players[&Player::Player1].current_card.unwrap();
+    let item_at = *model
+      .board
+      .placed
+      .iter()
+      .find(|(_, placed)| placed.tile.marking == Some(TileMarking::Item(item)))
+      .unwrap()
+      .0;
+    model
+      .board
+      .place_player(&Player::Player1, &item_at)
+      .unwrap();
+    let before = model.clone();
+
+    let move_player = Command::MovePlayer(Player::Player1, item_at);
+    apply_command(&mut model, &mut history, Player::Player1, &move_player).unwrap();
+    assert!(model.players[&Player::Player1].found_cards.contains(&item));
+    let after = model.clone();
+
+    apply_command(&mut model, &mut history, Player::Player1, &Command::Undo).unwrap();
+    assert_same_game(&model, &before);
+
+    apply_command(&mut model, &mut history, Player::Player1, &Command::Redo).unwrap();
+    assert_same_game(&model, &after);
+  }
+}
+
+#[test]
+fn mcts_bots_search_with_the_cards_dealt_as_the_game_deals_them() {
+  for dealing in [
+    DealingPolicy::EqualHands,
+    DealingPolicy::FixedHandSize(1),
+    DealingPolicy::SharedPile,
+  ] {
+    let mut model = Model::new(&GameConfig {
+      dealing,
+      ..config(8)
+    })
+    .unwrap();
+    let mut history = History::default();
+    let mut bot = BotKind::Mcts(SearchBudget::Iterations(20)).create(8);
+
+    for _ in 0..2 {
+      let snapshot = Snapshot::for_player(&model, Player::Player1);
+      assert_eq!(snapshot.dealing, dealing);
+      let command = bot.choose(&snapshot);
+      apply_command(&mut model, &mut history, Player::Player1, &command).unwrap();
+    }
+    assert_eq!(model.current_player, Player::Player2);
+  }
+}
+
+/// Call `check` with every state of a game played by greedy bots, until someone wins
+fn for_every_state(seed: u64, mut check: impl FnMut(&Model)) {
+  let mut model = Model::new(&GameConfig {
+    players: HashSet::from([Player::Player1, Player::Player2, Player::Player3]),
+    dealing: DealingPolicy::EqualHands,
+    ..config(seed)
+  })
+  .unwrap();
+  let mut history = History::default();
+  let mut bots = [
+    BotKind::Greedy.create(1),
+    BotKind::Greedy.create(2),
+    BotKind::Greedy.create(3),
+  ];
+
+  check(&model);
+  while !model.is_game_over() {
+    let player = model.current_player;
+    let command = bots[player as usize].choose(&Snapshot::for_player(&model, player));
+    apply_command(&mut model, &mut history, player, &command).unwrap();
+    check(&model);
+  }
+}
+
+/// Everyone may see which cards each player has found and how many they have left, but never a
+/// card that is still to be found
+fn check_cards_are_public(model: &Model, snapshot: &Snapshot) {
+  let unfound: HashSet<Item> = model
+    .players
+    .values()
+    .flat_map(|cards| cards.current_card.iter().chain(&cards.hidden_cards))
+    .copied()
+    .collect();
+
+  for (player, cards) in &model.players {
+    let seen = &snapshot.players[player];
+    assert_eq!(seen.found, cards.found_cards);
+    assert!(seen.found.is_disjoint(&unfound));
+    assert_eq!(seen.num_remaining as usize, cards.remaining());
+  }
+}
+
+#[test]
+fn players_see_only_the_card_they_are_looking_for() {
+  for_every_state(2, |model| {
+    for (player, cards) in &model.players {
+      let snapshot = Snapshot::for_viewer(model, Viewer::Player(*player));
+      assert_eq!(snapshot.looking_for, cards.current_card);
+      check_cards_are_public(model, &snapshot);
+    }
+  });
+}
+
+#[test]
+fn players_never_see_their_opponents_cards() {
+  for_every_state(3, |model| {
+    let current = model.current_player;
+    let current_card = match model.players[&current].current_card {
+      Some(item) => item,
+      None => return,
+    };
+
+    let others = model
+      .players
+      .keys()
+      .filter(|player| **player != current)
+      .map(|player| Viewer::Player(*player));
+    for viewer in others.chain([Viewer::Spectator]) {
+      let snapshot = Snapshot::for_viewer(model, viewer);
+      assert_ne!(snapshot.looking_for, Some(current_card), "{:?}", viewer);
+      check_cards_are_public(model, &snapshot);
+    }
+  });
+}
+
+#[test]
+fn spectators_never_see_anyones_cards() {
+  for_every_state(4, |model| {
+    let snapshot = Snapshot::for_viewer(model, Viewer::Spectator);
+    assert_eq!(snapshot.looking_for, None);
+    check_cards_are_public(model, &snapshot);
+  });
+}
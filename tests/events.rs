@@ -0,0 +1,207 @@
+mod common;
+
+use common::{config, start_controller};
+use labyrinth::bot::{send, BotKind};
+use labyrinth::{Board, Command, GameEvent, Model, Player, Snapshot, TurnPhase, Viewer};
+use std::sync::mpsc::Receiver;
+
+fn pending(events: &Receiver<GameEvent>) -> Vec<GameEvent> {
+  events.try_iter().collect()
+}
+
+#[test]
+fn everyone_is_told_about_every_change() {
+  let (controller_tx, subscriptions, controller) =
+    start_controller(Model::new(&config(1)).unwrap());
+  let viewers = [
+    Viewer::Player(Player::Player1),
+    Viewer::Player(Player::Player2),
+    Viewer::Spectator,
+  ];
+  let receivers: Vec<_> = viewers
+    .iter()
+    .map(|viewer| subscriptions.subscribe(*viewer))
+    .collect();
+
+  let view = send(&controller_tx, Player::Player1, Command::NoOp).unwrap();
+  let (at, rotation) = view.to_board().legal_insertions()[0];
+  let view = send(
+    &controller_tx,
+    Player::Player1,
+    Command::InsertTile(at, rotation),
+  )
+  .unwrap();
+  for events in &receivers {
+    match &pending(events)[..] {
+      [GameEvent::TileInserted {
+        player: Player::Player1,
+        at: inserted_at,
+        rotation: inserted_rotation,
+        pushed_out,
+      }, GameEvent::TurnChanged {
+        player: Player::Player1,
+        phase: TurnPhase::Move,
+      }] => {
+        assert_eq!((*inserted_at, *inserted_rotation), (at, rotation));
+        assert_eq!(*pushed_out, view.spare_tile);
+      }
+      other => panic!("unexpected events {:?}", other),
+    }
+  }
+
+  // Nobody is told about commands that were not allowed
+  assert!(send(&controller_tx, Player::Player2, Command::Undo).is_err());
+  let stay = view.to_board().player_location(&Player::Player1).unwrap();
+  send(
+    &controller_tx,
+    Player::Player1,
+    Command::MovePlayer(Player::Player1, stay),
+  )
+  .unwrap();
+  for events in &receivers {
+    match &pending(events)[..] {
+      [GameEvent::PlayerMoved {
+        player: Player::Player1,
+        path,
+      }, GameEvent::TurnChanged {
+        player: Player::Player2,
+        phase: TurnPhase::InsertTile,
+      }] => assert_eq!(path, &vec![stay]),
+      other => panic!("unexpected events {:?}", other),
+    }
+  }
+
+  send(&controller_tx, Player::Player1, Command::Undo).unwrap();
+  for events in &receivers {
+    assert!(matches!(
+      &pending(events)[..],
+      [
+        GameEvent::Undone {
+          player: Player::Player1,
+          ..
+        },
+        GameEvent::TurnChanged {
+          player: Player::Player1,
+          phase: TurnPhase::Move,
+        }
+      ]
+    ));
+  }
+
+  drop(controller_tx);
+  controller.join().unwrap();
+}
+
+#[test]
+fn only_the_finder_is_told_which_card_they_look_for_next() {
+  let (controller_tx, subscriptions, controller) =
+    start_controller(Model::new(&config(3)).unwrap());
+  let player1 = subscriptions.subscribe(Viewer::Player(Player::Player1));
+  let spectator = subscriptions.subscribe(Viewer::Spectator);
+
+  // Let greedy bots play until player 1 finds a card
+  let mut bots = [BotKind::Greedy.create(1), BotKind::Greedy.create(2)];
+  let found = 'play: loop {
+    let snapshot = send(&controller_tx, Player::Player1, Command::NoOp).unwrap();
+    assert!(snapshot.standings.is_none());
+    let player = snapshot.next_player;
+    let snapshot = send(&controller_tx, player, Command::NoOp).unwrap();
+    let command = bots[player as usize].choose(&snapshot);
+    send(&controller_tx, player, command).unwrap();
+
+    for event in pending(&player1) {
+      if let GameEvent::CardFound {
+        player: Player::Player1,
+        item,
+        next,
+      } = event
+      {
+        let looking_for = send(&controller_tx, Player::Player1, Command::NoOp)
+          .unwrap()
+          .looking_for;
+        assert_eq!(next, looking_for);
+        break 'play item;
+      }
+    }
+  };
+
+  let seen_by_spectator = pending(&spectator)
+    .into_iter()
+    .find_map(|event| match event {
+      GameEvent::CardFound {
+        player: Player::Player1,
+        item,
+        next,
+      } => Some((item, next)),
+      _ => None,
+    });
+  assert_eq!(seen_by_spectator, Some((found, None)));
+
+  drop(controller_tx);
+  controller.join().unwrap();
+}
+
+/// Keep a spectator's board up to date from the events it is sent
+fn follow(board: &mut Board, events: &Receiver<GameEvent>) {
+  for event in pending(events) {
+    match event {
+      GameEvent::TileInserted {
+        at,
+        rotation,
+        pushed_out,
+        ..
+      } => {
+        board.insert_spare(at, rotation).unwrap();
+        assert!(board.spare == pushed_out);
+      }
+      GameEvent::PlayerMoved { player, path } => {
+        board.move_player(&player, path.last().unwrap()).unwrap()
+      }
+      GameEvent::Undone { game, .. } => *board = game.to_board(),
+      _ => {}
+    }
+  }
+}
+
+#[test]
+fn spectators_can_follow_the_board_through_undos() {
+  let model = Model::new(&config(2)).unwrap();
+  let mut board = Snapshot::for_viewer(&model, Viewer::Spectator).to_board();
+  let (controller_tx, subscriptions, controller) = start_controller(model);
+  let spectator = subscriptions.subscribe(Viewer::Spectator);
+
+  let mut play = |command: Command| {
+    let view = send(&controller_tx, Player::Player1, command).unwrap();
+    follow(&mut board, &spectator);
+    let actual = view.to_board();
+    assert_eq!(format!("{:?}", board), format!("{:?}", actual));
+    assert!(board.spare == actual.spare);
+    assert_eq!(board.forbidden_insertion(), actual.forbidden_insertion());
+    view
+  };
+
+  let view = play(Command::NoOp);
+  let insertions = view.to_board().legal_insertions();
+  let view = play(Command::InsertTile(insertions[0].0, insertions[0].1));
+  let board_now = view.to_board();
+  let from = board_now.player_location(&Player::Player1).unwrap();
+  let to = board_now
+    .reachable_from(&from)
+    .into_iter()
+    .max_by_key(|location| (location.0, location.1))
+    .unwrap();
+  play(Command::MovePlayer(Player::Player1, to));
+
+  // Take back the move and the insertion, then play a different turn
+  play(Command::Undo);
+  play(Command::Undo);
+  play(Command::Redo);
+  play(Command::Undo);
+  let (at, rotation) = insertions[insertions.len() - 1];
+  let view = play(Command::InsertTile(at, rotation));
+  let stay = view.to_board().player_location(&Player::Player1).unwrap();
+  play(Command::MovePlayer(Player::Player1, stay));
+
+  drop(controller_tx);
+  controller.join().unwrap();
+}
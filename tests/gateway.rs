@@ -0,0 +1,156 @@
+mod common;
+
+use common::{any_insertion, error_code, seated, serve_game, snapshot, Connected};
+use labyrinth::gateway::{serve_websockets, Gateway};
+use labyrinth::server::ServerMessage;
+use labyrinth::{Command, GameLog, Player, TurnPhase};
+use std::net::{SocketAddr, TcpStream};
+use std::thread::JoinHandle;
+use tungstenite::http::StatusCode;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+impl Connected for Socket {
+  fn next_message(&mut self) -> ServerMessage {
+    loop {
+      if let Message::Text(text) = self.read().unwrap() {
+        return serde_json::from_str(text.as_str()).unwrap();
+      }
+    }
+  }
+}
+
+/// Start serving a game between players 1 and 2 over WebSockets on a free port
+fn start(gateway: Gateway) -> (SocketAddr, JoinHandle<GameLog>) {
+  serve_game(move |listener, controller_tx, subscriptions| {
+    serve_websockets(&gateway, listener, controller_tx, subscriptions)
+  })
+}
+
+fn two_clients() -> Gateway {
+  Gateway::new(&[Player::Player1, Player::Player2], Vec::new(), 1)
+}
+
+fn connect(address: SocketAddr, token: &str) -> tungstenite::Result<Socket> {
+  let url = format!("ws://{}/?token={}", address, token);
+  Ok(tungstenite::connect(url)?.0)
+}
+
+fn send(socket: &mut Socket, command: &Command) {
+  let text = serde_json::to_string(command).unwrap();
+  socket.send(Message::text(text)).unwrap();
+}
+
+/// Close the connection and wait for the gateway to agree
+fn leave(mut socket: Socket) {
+  socket.close(None).unwrap();
+  while socket.read().is_ok() {}
+}
+
+#[test]
+fn clients_are_seated_by_their_token_in_any_order() {
+  let gateway = two_clients();
+  let tokens = gateway.tokens.clone();
+  let (address, handle) = start(gateway);
+
+  let mut second = connect(address, &tokens[&Player::Player2]).unwrap();
+  assert_eq!(seated(&mut second), Player::Player2);
+  let mut first = connect(address, &tokens[&Player::Player1]).unwrap();
+  assert_eq!(seated(&mut first), Player::Player1);
+
+  let first_view = snapshot(&mut first);
+  let second_view = snapshot(&mut second);
+  assert_eq!(first_view.next_player, Player::Player1);
+  assert_ne!(first_view.looking_for, second_view.looking_for);
+
+  leave(first);
+  leave(second);
+  assert!(handle.join().unwrap().events.is_empty());
+}
+
+#[test]
+fn unknown_tokens_and_taken_seats_are_turned_away() {
+  let gateway = two_clients();
+  let tokens = gateway.tokens.clone();
+  let (address, handle) = start(gateway);
+
+  let status = |result: tungstenite::Result<Socket>| match result {
+    Err(tungstenite::Error::Http(response)) => response.status(),
+    _ => panic!("expected to be turned away"),
+  };
+  assert_eq!(status(connect(address, "guess")), StatusCode::UNAUTHORIZED);
+  let mut near_miss = tokens[&Player::Player1].clone();
+  let last = if near_miss.pop() == Some('0') {
+    '1'
+  } else {
+    '0'
+  };
+  near_miss.push(last);
+  assert_eq!(
+    status(connect(address, &near_miss)),
+    StatusCode::UNAUTHORIZED
+  );
+
+  let mut first = connect(address, &tokens[&Player::Player1]).unwrap();
+  assert_eq!(seated(&mut first), Player::Player1);
+  assert_eq!(
+    status(connect(address, &tokens[&Player::Player1])),
+    StatusCode::CONFLICT
+  );
+
+  let mut second = connect(address, &tokens[&Player::Player2]).unwrap();
+  assert_eq!(seated(&mut second), Player::Player2);
+
+  leave(first);
+  leave(second);
+  handle.join().unwrap();
+}
+
+#[test]
+fn accepted_commands_are_sent_to_everyone_and_errors_only_to_the_sender() {
+  let gateway = two_clients();
+  let tokens = gateway.tokens.clone();
+  let (address, handle) = start(gateway);
+
+  let mut first = connect(address, &tokens[&Player::Player1]).unwrap();
+  seated(&mut first);
+  let mut second = connect(address, &tokens[&Player::Player2]).unwrap();
+  seated(&mut second);
+  let view = snapshot(&mut first);
+  snapshot(&mut second);
+
+  let insertion = any_insertion(&view);
+  send(&mut second, &insertion);
+  assert_eq!(error_code(&mut second), "not_your_turn");
+  second.send(Message::text("not a command")).unwrap();
+  assert_eq!(error_code(&mut second), "invalid_message");
+
+  send(&mut first, &insertion);
+  assert_eq!(snapshot(&mut first).turn_phase, TurnPhase::Move);
+  let pushed = snapshot(&mut second);
+  assert_eq!(pushed.turn_phase, TurnPhase::Move);
+  assert_eq!(pushed.next_player, Player::Player1);
+
+  leave(first);
+  leave(second);
+  assert_eq!(handle.join().unwrap().events.len(), 1);
+}
+
+#[test]
+fn a_client_that_never_says_anything_does_not_stop_anyone_else_connecting() {
+  let gateway = two_clients();
+  let tokens = gateway.tokens.clone();
+  let (address, handle) = start(gateway);
+
+  let _silent = TcpStream::connect(address).unwrap();
+  let mut first = connect(address, &tokens[&Player::Player1]).unwrap();
+  assert_eq!(seated(&mut first), Player::Player1);
+  let mut second = connect(address, &tokens[&Player::Player2]).unwrap();
+  assert_eq!(seated(&mut second), Player::Player2);
+
+  leave(first);
+  leave(second);
+  handle.join().unwrap();
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::config;
+use labyrinth::{Item, LabyrinthError, Model, Player, TileMarking};
+
+/// A game where every card has been dealt
+fn model() -> Model {
+  Model::new(&config(1)).unwrap()
+}
+
+fn broken_invariant(model: &Model) -> String {
+  match Model::load_json(&model.save_json().unwrap()) {
+    Err(LabyrinthError::BrokenInvariant { reason }) => reason,
+    other => panic!("expected a broken invariant, got {:?}", other.map(|_| ())),
+  }
+}
+
+#[test]
+fn games_load_as_they_were_saved() {
+  let model = model();
+  let loaded = Model::load_json(&model.save_json().unwrap()).unwrap();
+  for (player, cards) in &model.players {
+    assert_eq!(loaded.players[player].current_card, cards.current_card);
+    assert_eq!(loaded.players[player].hidden_cards, cards.hidden_cards);
+  }
+}
+
+#[test]
+fn cards_for_items_missing_from_the_board_are_rejected() {
+  let mut model = model();
+  let goblet = model
+    .board
+    .placed
+    .values_mut()
+    .find(|tile| tile.tile.marking == Some(TileMarking::Item(Item::Goblet)))
+    .unwrap();
+  goblet.tile.marking = None;
+
+  assert_eq!(
+    broken_invariant(&model),
+    "Goblet has been dealt but is not on the board"
+  );
+}
+
+#[test]
+fn cards_dealt_twice_are_rejected() {
+  let mut model = model();
+  model
+    .players
+    .get_mut(&Player::Player1)
+    .unwrap()
+    .hidden_cards
+    .push(Item::Crown);
+
+  assert_eq!(
+    broken_invariant(&model),
+    "Crown has been dealt more than once"
+  );
+}
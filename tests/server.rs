@@ -0,0 +1,149 @@
+mod common;
+
+use common::{any_insertion, error_code, seated, serve_game, snapshot, Connected};
+use labyrinth::bot::BotKind;
+use labyrinth::server::{serve, Client, Server, ServerMessage};
+use labyrinth::{Command, GameLog, Player, TurnPhase};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread::JoinHandle;
+
+impl Connected for Client {
+  fn next_message(&mut self) -> ServerMessage {
+    self.receive().unwrap()
+  }
+}
+
+/// Start serving a game between players 1 and 2 on a free port
+fn start(server: Server) -> (SocketAddr, JoinHandle<GameLog>) {
+  serve_game(move |listener, controller_tx, subscriptions| {
+    serve(&server, listener, controller_tx, subscriptions)
+  })
+}
+
+fn two_clients() -> Server {
+  Server {
+    remote_players: vec![Player::Player1, Player::Player2],
+    bots: Vec::new(),
+    seed: 1,
+  }
+}
+
+#[test]
+fn clients_are_seated_in_the_order_they_connect() {
+  let (address, handle) = start(two_clients());
+
+  let mut first = Client::connect(address).unwrap();
+  assert_eq!(seated(&mut first), Player::Player1);
+  let mut second = Client::connect(address).unwrap();
+  assert_eq!(seated(&mut second), Player::Player2);
+
+  let first_view = snapshot(&mut first);
+  let second_view = snapshot(&mut second);
+  assert_eq!(first_view.next_player, Player::Player1);
+  assert_eq!(first_view.turn_phase, TurnPhase::InsertTile);
+  assert!(first_view.looking_for.is_some());
+  assert_ne!(first_view.looking_for, second_view.looking_for);
+
+  drop(first);
+  drop(second);
+  assert!(handle.join().unwrap().events.is_empty());
+}
+
+#[test]
+fn commands_are_answered_and_changes_are_sent_to_everyone() {
+  let (address, handle) = start(two_clients());
+
+  let mut first = Client::connect(address).unwrap();
+  seated(&mut first);
+  let mut second = Client::connect(address).unwrap();
+  seated(&mut second);
+  let view = snapshot(&mut first);
+  snapshot(&mut second);
+
+  second.send(&any_insertion(&view)).unwrap();
+  assert_eq!(error_code(&mut second), "not_your_turn");
+
+  first.send(&any_insertion(&view)).unwrap();
+  assert_eq!(snapshot(&mut first).turn_phase, TurnPhase::Move);
+  let pushed = snapshot(&mut second);
+  assert_eq!(pushed.turn_phase, TurnPhase::Move);
+  assert_eq!(pushed.next_player, Player::Player1);
+
+  first.send(&Command::Undo).unwrap();
+  assert_eq!(snapshot(&mut first).turn_phase, TurnPhase::InsertTile);
+  assert_eq!(snapshot(&mut second).turn_phase, TurnPhase::InsertTile);
+
+  drop(first);
+  drop(second);
+  assert_eq!(handle.join().unwrap().events.len(), 2);
+}
+
+#[test]
+fn clients_can_speak_the_protocol_without_the_library() {
+  let (address, handle) = start(Server {
+    remote_players: vec![Player::Player1],
+    bots: Vec::new(),
+    seed: 1,
+  });
+
+  let mut stream = TcpStream::connect(address).unwrap();
+  let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+  assert_eq!(
+    lines.next().unwrap().unwrap(),
+    r#"{"Seated":{"player":"Player1"}}"#
+  );
+  assert!(lines
+    .next()
+    .unwrap()
+    .unwrap()
+    .starts_with(r#"{"Snapshot":{"#));
+
+  stream.write_all(b"not a command\n").unwrap();
+  assert!(lines
+    .next()
+    .unwrap()
+    .unwrap()
+    .starts_with(r#"{"Error":{"code":"invalid_message","number":404,"#));
+
+  stream.write_all(b"\"Undo\"\n").unwrap();
+  assert!(lines
+    .next()
+    .unwrap()
+    .unwrap()
+    .starts_with(r#"{"Error":{"code":"nothing_to_undo","number":301,"#));
+
+  drop(stream);
+  drop(lines);
+  assert!(handle.join().unwrap().events.is_empty());
+}
+
+#[test]
+fn bots_play_against_clients_and_stop_when_they_leave() {
+  let (address, handle) = start(Server {
+    remote_players: vec![Player::Player1],
+    bots: vec![(Player::Player2, BotKind::Greedy)],
+    seed: 1,
+  });
+
+  let mut client = Client::connect(address).unwrap();
+  assert_eq!(seated(&mut client), Player::Player1);
+  let view = snapshot(&mut client);
+
+  client.send(&any_insertion(&view)).unwrap();
+  let view = snapshot(&mut client);
+  let stay = view.to_board().player_location(&Player::Player1).unwrap();
+  client
+    .send(&Command::MovePlayer(Player::Player1, stay))
+    .unwrap();
+
+  // The bot's insertion and move are sent to the client as it makes them
+  let mut view = snapshot(&mut client);
+  while view.next_player != Player::Player1 {
+    view = snapshot(&mut client);
+  }
+  assert_eq!(view.turn_phase, TurnPhase::InsertTile);
+
+  drop(client);
+  assert_eq!(handle.join().unwrap().events.len(), 4);
+}